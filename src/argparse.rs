@@ -1,12 +1,10 @@
 use super::config::models::*;
-use crate::subcommands::subcommand::MrtSubcommand;
+use crate::subcommands::subcommand::Subcommand;
 use crate::util::format_path;
-use anyhow::Result;
 use args::*;
 use clap::ArgMatches;
-use std::process::exit;
 
-#[derive(Debug, PartialOrd, PartialEq)]
+#[derive(Debug, PartialOrd, PartialEq, Eq)]
 pub struct ParsedArgs {
     pub tags: Vec<String>,
     pub before_tags: Vec<String>,
@@ -21,6 +19,17 @@ impl ParsedArgs {
             after_tags: Vec::new(),
         }
     }
+
+    /// Uses the default tags from the config when no tags were given on the command line or in `MRT_DEFAULT_TAGS`
+    pub fn or_default_tags(mut self, default_tags: &[String]) -> Self {
+        if self.tags.is_empty() {
+            self.tags = default_tags
+                .iter()
+                .map(|t| format!("{}{}", TAG_PREFIX, t))
+                .collect();
+        }
+        self
+    }
 }
 
 pub mod args {
@@ -32,9 +41,16 @@ pub mod args {
     pub const PANIC_ON_NON_ZERO_ARG: &str = "panic-on-nonzero";
     pub const ONLY_IN_MODIFIED: &str = "modified-only";
     pub const USE_LAST_PATHS: &str = "previous-paths";
+    pub const EXPAND_SUBMODULES: &str = "submodules";
+    pub const EXPAND_WORKTREES: &str = "worktrees";
+    pub const PROFILE_ARG: &str = "profile";
+    pub const JSON_OUTPUT_ARG: &str = "json";
 }
 
 const TAG_ENV_VAR: &str = "MRT_DEFAULT_TAGS";
+pub const PROFILE_ENV_VAR: &str = "MRT_PROFILE";
+/// Arguments to mrt that take a value, so the value isn't mistaken for the command to execute
+const ARGS_WITH_VALUE: &[&str] = &["--profile"];
 
 fn find_tags_in_args(args: &[String], subcommand_names: &[&String]) -> ParsedArgs {
     let any_tags = args.iter().any(|t| t.starts_with(TAG_PREFIX));
@@ -57,9 +73,16 @@ fn find_tags_in_args(args: &[String], subcommand_names: &[&String]) -> ParsedArg
 
         let is_tag_before_cmd =
             arg.starts_with(TAG_PREFIX) && (acc.after_tags.is_empty() && !double_dash);
+        let is_value_of_previous_arg = acc.after_tags.is_empty()
+            && !double_dash
+            && acc
+                .before_tags
+                .last()
+                .is_some_and(|previous| ARGS_WITH_VALUE.contains(&previous.as_str()));
 
         match arg {
             a if a == "--" => double_dash = true,
+            a if is_value_of_previous_arg => acc.before_tags.push(a.clone()),
             a if is_tag_before_cmd => acc.tags.push(a.clone()),
             a if (found_tags_or_no_tags && isnt_subcmd) || double_dash => {
                 has_encountered_non_subcommand = true;
@@ -77,29 +100,26 @@ fn find_tags_in_args(args: &[String], subcommand_names: &[&String]) -> ParsedArg
 }
 
 fn get_tags_from_env() -> Vec<String> {
-    match std::env::var(TAG_ENV_VAR) {
-        Ok(tag_string) => {
+    std::env::var(TAG_ENV_VAR).map_or_else(
+        |_| vec![],
+        |tag_string| {
             let split = tag_string.split(',');
             split.map(|t| format!("+{}", t.trim())).collect()
-        }
-        _ => vec![],
-    }
+        },
+    )
 }
 
-pub fn parse_arguments(subcommands: &[MrtSubcommand]) -> ParsedArgs {
-    let subcommand_names: Vec<&String> = subcommands.iter().map(|x| &x.name).collect();
+pub fn parse_arguments(subcommands: &[Box<dyn Subcommand>]) -> ParsedArgs {
+    let names: Vec<String> = subcommands.iter().map(|x| x.name().to_string()).collect();
+    let subcommand_names: Vec<&String> = names.iter().collect();
 
     let args = std::env::args();
     let args_vec: Vec<String> = args.collect();
     find_tags_in_args(&args_vec, &subcommand_names)
 }
 
-pub fn handle_args_to_self(
-    subcommands: Vec<MrtSubcommand>,
-    args: &ArgMatches,
-    parsed_arguments: &ParsedArgs,
-    config: ConfigFile,
-) -> Result<ConfigFile> {
+/// Handles the arguments to mrt itself that are used together with a subcommand or command
+pub fn handle_args_to_self(args: &ArgMatches, config: &ConfigFile) {
     if args.is_present(LIST_TAGS_ARG) {
         println!("Config Version: {}", &config.version);
         for (tag_name, tag) in &config.tags {
@@ -110,17 +130,6 @@ pub fn handle_args_to_self(
             }
         }
     }
-
-    match args.subcommand() {
-        (subcmd, Some(matched)) => {
-            let found_cmd = subcommands.iter().find(|cmd| cmd.name == subcmd);
-            if let Some(found) = found_cmd {
-                (found.run_subcommand)(matched, parsed_arguments, config)
-            };
-            exit(0)
-        }
-        _ => Ok(config),
-    }
 }
 
 #[cfg(test)]
@@ -134,7 +143,10 @@ mod test {
     }
 
     fn subcmd_names() -> Vec<String> {
-        let names: Vec<String> = get_subcommands().iter().map(|x| x.name.clone()).collect();
+        let names: Vec<String> = get_subcommands()
+            .iter()
+            .map(|x| x.name().to_string())
+            .collect();
         names
     }
 
@@ -283,4 +295,44 @@ mod test {
 
         assert_eq!(result1, expected1);
     }
+
+    #[test]
+    fn test_value_of_profile_is_not_parsed_as_command() {
+        let test_args1: Vec<String> =
+            to_string_vec(vec!["mrt", "--profile", "work", "git", "status"]);
+        let test_args2: Vec<String> =
+            to_string_vec(vec!["mrt", "+testtag", "--profile", "work", "status"]);
+
+        let expected1 = ParsedArgs {
+            tags: to_string_vec(vec![]),
+            before_tags: to_string_vec(vec!["mrt", "--profile", "work"]),
+            after_tags: to_string_vec(vec!["git", "status"]),
+        };
+
+        let expected2 = ParsedArgs {
+            tags: to_string_vec(vec!["+testtag"]),
+            before_tags: to_string_vec(vec!["mrt", "--profile", "work", "status"]),
+            after_tags: to_string_vec(vec![]),
+        };
+
+        let names = subcmd_names();
+        let s: Vec<&String> = names.iter().collect();
+        let result1 = find_tags_in_args(&test_args1, &s);
+        let result2 = find_tags_in_args(&test_args2, &s);
+
+        assert_eq!(result1, expected1);
+        assert_eq!(result2, expected2);
+        assert_eq!(
+            result1
+                .or_default_tags(&to_string_vec(vec!["backend"]))
+                .tags,
+            to_string_vec(vec!["+backend"])
+        );
+        assert_eq!(
+            result2
+                .or_default_tags(&to_string_vec(vec!["backend"]))
+                .tags,
+            to_string_vec(vec!["+testtag"])
+        );
+    }
 }
@@ -0,0 +1,65 @@
+use super::models::ConfigFile;
+use anyhow::Result;
+use serde_json::Value;
+use std::path::Path;
+
+/// File format of a config, detected by the file extension
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum ConfigFormat {
+    Json,
+    Toml,
+    Yaml,
+}
+
+impl ConfigFormat {
+    /// Files without a known extension are read as json, like mrt always has
+    pub fn from_path(path: &Path) -> Self {
+        match path.extension().and_then(|e| e.to_str()) {
+            Some("toml") => Self::Toml,
+            Some("yaml") | Some("yml") => Self::Yaml,
+            _ => Self::Json,
+        }
+    }
+
+    /// Parses the config into a generic value, so it can be migrated before it is read as a `ConfigFile`
+    pub fn parse(self, contents: &str) -> Result<Value> {
+        Ok(match self {
+            Self::Json => serde_json::from_str(contents)?,
+            Self::Toml => toml::from_str(contents)?,
+            Self::Yaml => serde_norway::from_str(contents)?,
+        })
+    }
+
+    pub fn serialize(self, config: &ConfigFile) -> Result<String> {
+        Ok(match self {
+            Self::Json => serde_json::to_string_pretty(config)?,
+            Self::Toml => toml::to_string_pretty(config)?,
+            Self::Yaml => serde_norway::to_string(config)?,
+        })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_format_from_extension() {
+        assert_eq!(
+            ConfigFormat::from_path(Path::new("/home/user/.mrtconfig.json")),
+            ConfigFormat::Json
+        );
+        assert_eq!(
+            ConfigFormat::from_path(Path::new("/home/user/.mrtconfig.toml")),
+            ConfigFormat::Toml
+        );
+        assert_eq!(
+            ConfigFormat::from_path(Path::new("/home/user/.mrtconfig.yml")),
+            ConfigFormat::Yaml
+        );
+        assert_eq!(
+            ConfigFormat::from_path(Path::new("/home/user/config")),
+            ConfigFormat::Json
+        );
+    }
+}
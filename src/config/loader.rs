@@ -1,19 +1,49 @@
+use super::format::ConfigFormat;
+use super::migrations;
 use super::models::*;
 
-use super::super::util::expand_pathbuf;
+use super::super::util::{expand_pathbuf, normalize_path, with_file_lock, write_file_atomically};
+use crate::state;
 use anyhow::{anyhow, Result};
+use colored::Colorize;
 use std::collections::HashMap;
 use std::fs::File;
 use std::io::prelude::*;
 use std::path::{Path, PathBuf};
 
 const CONFIG_ENV_NAME: &str = "MRT_CONFIG_PATH";
+/// Workspace config, usually committed to a meta repository, found by walking up from the current directory
+const WORKSPACE_CONFIG_NAMES: &[&str] = &[".mrt.toml", ".mrt.yaml", ".mrt.yml", ".mrt.json"];
+/// Personal overrides of the workspace config, next to it and usually ignored by git
+const LOCAL_CONFIG_NAMES: &[&str] = &[
+    ".mrt.local.toml",
+    ".mrt.local.yaml",
+    ".mrt.local.yml",
+    ".mrt.local.json",
+];
+/// Config file names looked for in the home directory, in order of preference
+const CONFIG_FILE_NAMES: &[&str] = &[
+    ".mrtconfig.json",
+    ".mrtconfig.toml",
+    ".mrtconfig.yaml",
+    ".mrtconfig.yml",
+];
 
 pub fn load_config(path: &Path) -> Result<ConfigFile> {
     match read_file_to_string(path) {
         Ok(config_string) => {
-            let data: ConfigFile = serde_json::from_str(&config_string)?;
-            Ok(expand_config_paths(data))
+            let (data, schema_version) = parse_and_migrate(path, &config_string)?;
+            let data = if schema_version == migrations::CURRENT_SCHEMA_VERSION {
+                data
+            } else {
+                // Migrating rewrites the config, so it's done while locked like any other change to it.
+                // The config is read again, in case a concurrent run of mrt migrated it first
+                with_file_lock(path, || load_raw_config(path)).unwrap_or_else(|e| {
+                    warn_unsaved_migration(path, &e);
+                    data
+                })
+            };
+            Ok(expand_config_paths(data, path))
         }
         _ => {
             eprintln!(
@@ -25,48 +55,275 @@ pub fn load_config(path: &Path) -> Result<ConfigFile> {
     }
 }
 
-pub fn store_previous_paths(mut config: ConfigFile, paths: &[PathBuf]) -> Result<ConfigFile> {
-    if !paths.is_empty() {
-        config.last_paths = Some(paths.to_owned());
-        save_config(config)
-    } else {
-        Ok(config)
+/// Loads the global config, with the workspace and local configs that apply in `dir` merged over it.
+/// Tags in a later layer replace tags with the same name in earlier ones, also within profiles
+pub fn load_layered_config(global_path: &Path, dir: &Path) -> Result<ConfigFile> {
+    let mut config = load_config(global_path)?;
+
+    for layer_path in get_config_layers(dir) {
+        let layer = read_config_layer(&layer_path)?;
+        merge_config_layer(&mut config, layer);
     }
+    Ok(config)
 }
 
-/** Expands paths in config from shorthand to absolute paths */
-fn expand_config_paths(mut config: ConfigFile) -> ConfigFile {
-    let mut tags_after_expand: HashMap<String, Tag> = HashMap::new();
-    for (tag_name, tag) in &config.tags {
-        let paths = tag
-            .paths
-            .iter()
-            .map(|p| expand_pathbuf(p.clone()))
-            .collect::<Vec<PathBuf>>();
-        tags_after_expand.insert(tag_name.clone(), Tag { paths });
+fn merge_config_layer(config: &mut ConfigFile, layer: ConfigFile) {
+    config.tags.extend(layer.tags);
+    if !layer.default_tags.is_empty() {
+        config.default_tags = layer.default_tags;
     }
 
-    config.tags = tags_after_expand;
-    config
+    for (profile_name, layer_profile) in layer.profiles {
+        let profile = config.profiles.entry(profile_name).or_default();
+        profile.tags.extend(layer_profile.tags);
+        if !layer_profile.default_tags.is_empty() {
+            profile.default_tags = layer_profile.default_tags;
+        }
+    }
 }
 
-pub fn save_config(config: ConfigFile) -> Result<ConfigFile> {
-    let config_path = get_config_path();
-    match config_path {
-        Some(path) => save_config_at(path.as_path(), &config).map(|()| config),
-        None => Err(anyhow!("Could not detect correct config path")),
+/// Adds the tags of the profile `name` to the top level tags and uses its default tags, if it has any
+pub fn select_profile(mut config: ConfigFile, name: &str) -> Result<ConfigFile> {
+    let profile = config.profiles.remove(name).ok_or_else(|| {
+        let mut profile_names: Vec<&str> = config.profiles.keys().map(String::as_str).collect();
+        profile_names.sort_unstable();
+        if profile_names.is_empty() {
+            anyhow!(
+                "Profile '{}' not found, there are no profiles in the config",
+                name
+            )
+        } else {
+            anyhow!(
+                "Profile '{}' not found, available profiles are: {}",
+                name,
+                profile_names.join(", ")
+            )
+        }
+    })?;
+
+    config.tags.extend(profile.tags);
+    if !profile.default_tags.is_empty() {
+        config.default_tags = profile.default_tags;
     }
+    Ok(config)
 }
 
-fn save_config_at(path: &Path, config_struct: &ConfigFile) -> Result<()> {
-    let data = serde_json::to_string_pretty(config_struct)?;
+/// Workspace and local config files that apply in `dir`, in the order they are merged
+pub fn get_config_layers(dir: &Path) -> Vec<PathBuf> {
+    let find_in = |dir: &Path, names: &[&str]| {
+        names
+            .iter()
+            .map(|name| dir.join(name))
+            .find(|path| path.is_file())
+    };
+
+    dir.ancestors()
+        .find_map(|ancestor| find_in(ancestor, WORKSPACE_CONFIG_NAMES))
+        .map(|workspace_path| {
+            let workspace_dir = workspace_path.parent().unwrap_or(dir).to_path_buf();
+            std::iter::once(workspace_path)
+                .chain(find_in(&workspace_dir, LOCAL_CONFIG_NAMES))
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+/// Reads a workspace or local config.
+/// These files are often shared, so they are migrated in memory rather than rewritten
+fn read_config_layer(path: &Path) -> Result<ConfigFile> {
+    Ok(expand_config_paths(load_raw_config_layer(path)?, path))
+}
+
+/// Loads a workspace or local config as it is stored, without expanding paths
+pub fn load_raw_config_layer(path: &Path) -> Result<ConfigFile> {
+    let config_string = read_file_to_string(path)?;
+    let (layer, _) = parse_and_migrate(path, &config_string)?;
+    Ok(layer)
+}
 
-    let mut file = File::create(path)?;
-    file.write_all(data.as_bytes())?;
+/// Loads the config as it is stored, without expanding paths.
+/// The config lock should be held, since the config is rewritten if it needs to be migrated
+pub fn load_raw_config(path: &Path) -> Result<ConfigFile> {
+    load_raw_config_with_state(path, state::loader::get_state_path().ok().as_deref())
+}
 
+/// Loads the config as it is stored, moving previous paths kept in it by older versions of mrt to the state at `state_path`
+fn load_raw_config_with_state(path: &Path, state_path: Option<&Path>) -> Result<ConfigFile> {
+    let config_string = read_file_to_string(path)?;
+    parse_config(path, &config_string, state_path)
+}
+
+/// Parses the config, migrating it and saving the result if it was written by an older version of mrt.
+/// A config that can't be written, like one in a read-only store, is only migrated in memory
+fn parse_config(path: &Path, config_string: &str, state_path: Option<&Path>) -> Result<ConfigFile> {
+    let (migrated, schema_version) = parse_and_migrate(path, config_string)?;
+    if schema_version == migrations::CURRENT_SCHEMA_VERSION {
+        return Ok(migrated);
+    }
+
+    if let Err(e) = save_migrated_config(path, config_string, schema_version, &migrated, state_path)
+    {
+        warn_unsaved_migration(path, &e);
+    }
+    Ok(migrated)
+}
+
+/// Saves the migrated config, keeping the original config as a backup next to it
+fn save_migrated_config(
+    path: &Path,
+    config_string: &str,
+    schema_version: u64,
+    migrated: &ConfigFile,
+    state_path: Option<&Path>,
+) -> Result<()> {
+    let backup_path = get_backup_path(path, schema_version);
+    write_file_atomically(&backup_path, config_string.as_bytes())?;
+    move_legacy_previous_paths(path, config_string, state_path);
+    save_config_at(path, migrated)?;
+    eprintln!(
+        "Migrated config at '{}' from schema version {} to {}, the old config is kept at '{}'",
+        path.display(),
+        schema_version,
+        migrated.schema_version,
+        backup_path.display()
+    );
     Ok(())
 }
 
+fn warn_unsaved_migration(path: &Path, error: &anyhow::Error) {
+    eprintln!(
+        "{} '{}', {}\n{}",
+        "WARNING: Could not save the migrated config at".yellow(),
+        path.display(),
+        "it is migrated again every time it is loaded...".yellow(),
+        error
+    );
+}
+
+/// Moves previous paths stored in the config by older versions of mrt to the state, so `-L` keeps working.
+/// Failing to move them should not stop the config from being migrated, so errors are only printed
+fn move_legacy_previous_paths(path: &Path, config_string: &str, state_path: Option<&Path>) {
+    let last_paths = ConfigFormat::from_path(path)
+        .parse(config_string)
+        .map(|value| migrations::get_legacy_last_paths(&value))
+        .unwrap_or_default();
+    if last_paths.is_empty() {
+        return;
+    }
+
+    let stored = state_path
+        .ok_or_else(|| anyhow!("Could not detect correct state path"))
+        .and_then(|state_path| {
+            state::loader::store_legacy_previous_paths_at(state_path, &last_paths)
+        });
+    if let Err(e) = stored {
+        eprintln!(
+            "{}\n{}",
+            "WARNING: Could not move previous paths from the config to the state...".yellow(),
+            e
+        );
+    }
+}
+
+/// Parses the config and migrates it to the current schema, returning it with the schema version it had
+fn parse_and_migrate(path: &Path, config_string: &str) -> Result<(ConfigFile, u64)> {
+    let value = ConfigFormat::from_path(path).parse(config_string)?;
+    let schema_version = migrations::get_schema_version(&value);
+    if schema_version == migrations::CURRENT_SCHEMA_VERSION {
+        return Ok((serde_json::from_value(value)?, schema_version));
+    }
+
+    let migrated = migrations::migrate(value)
+        .map_err(|e| anyhow!("Could not migrate '{}': {}", path.display(), e))?;
+    Ok((serde_json::from_value(migrated)?, schema_version))
+}
+
+fn get_backup_path(path: &Path, schema_version: u64) -> PathBuf {
+    let mut backup_name = path.file_name().unwrap_or_default().to_os_string();
+    backup_name.push(format!(".v{}.bak", schema_version));
+    path.with_file_name(backup_name)
+}
+
+/** Expands paths in config from shorthand to absolute paths */
+fn expand_config_paths(mut config: ConfigFile, config_path: &Path) -> ConfigFile {
+    config.tags = expand_tag_paths(config.tags, config_path);
+    for profile in config.profiles.values_mut() {
+        profile.tags = expand_tag_paths(std::mem::take(&mut profile.tags), config_path);
+    }
+    config
+}
+
+/// Paths that can't be expanded are kept aside in the tag, to be reported when the tag is used
+fn expand_tag_paths(tags: HashMap<String, Tag>, config_path: &Path) -> HashMap<String, Tag> {
+    tags.into_iter()
+        .map(|(tag_name, tag)| {
+            let mut expanded = Tag::default();
+            for path in tag.paths {
+                match resolve_config_path(config_path, &path) {
+                    Ok(resolved) => expanded.paths.push(resolved),
+                    Err(e) => expanded
+                        .unexpandable
+                        .push((path, format!("{}, in '{}'", e, config_path.display()))),
+                }
+            }
+            (tag_name, expanded)
+        })
+        .collect()
+}
+
+/// Resolves a path as it is stored in the config at `config_path`.
+/// `~` and environment variables are expanded, and relative paths are relative to the directory of the config file,
+/// so a config with relative paths can be shared across machines
+pub fn resolve_config_path(config_path: &Path, path: &Path) -> Result<PathBuf> {
+    let base_dir = get_config_dir(config_path);
+    Ok(normalize_path(
+        &base_dir.join(expand_pathbuf(path.to_path_buf())?),
+    ))
+}
+
+/// Absolute path of the directory containing the config at `config_path`
+pub fn get_config_dir(config_path: &Path) -> PathBuf {
+    let parent = config_path.parent().unwrap_or_else(|| Path::new(""));
+    let absolute_parent = std::env::current_dir()
+        .map(|cwd| cwd.join(parent))
+        .unwrap_or_else(|_| parent.to_path_buf());
+    normalize_path(&absolute_parent)
+}
+
+/// Reads the config, applies `modify` and saves the result, while holding the config lock.
+/// The config is read again after locking, so changes from concurrent runs of mrt are kept.
+/// `modify` gets the config as it is stored, so relative and unexpanded paths are kept as they are
+pub fn update_config<F>(modify: F) -> Result<ConfigFile>
+where
+    F: FnOnce(ConfigFile, &Path) -> Result<ConfigFile>,
+{
+    with_config_lock(|path| {
+        let stored = if path.exists() {
+            load_raw_config(path)?
+        } else {
+            ConfigFile::new()
+        };
+        let config = modify(stored, path)?;
+        save_config_at(path, &config)?;
+        Ok(config)
+    })
+}
+
+/// Runs `f` with the path of the config while holding an exclusive advisory lock on it
+pub fn with_config_lock<T, F>(f: F) -> Result<T>
+where
+    F: FnOnce(&Path) -> Result<T>,
+{
+    let config_path =
+        get_config_path().ok_or_else(|| anyhow!("Could not detect correct config path"))?;
+    with_file_lock(&config_path, || f(&config_path))
+}
+
+pub fn save_config_at(path: &Path, config_struct: &ConfigFile) -> Result<()> {
+    let data = ConfigFormat::from_path(path).serialize(config_struct)?;
+    write_file_atomically(path, data.as_bytes())
+}
+
 fn read_file_to_string(path: &Path) -> Result<String> {
     let mut file = File::open(path)?;
     let mut contents = String::new();
@@ -80,9 +337,12 @@ pub fn get_config_path() -> Option<PathBuf> {
         Ok(path) => Some(PathBuf::from(path)),
         _ => {
             let config_dir = dirs::home_dir()?;
-            let config_filename = Path::new(".mrtconfig.json");
-            let combined_path = config_dir.join(config_filename);
-            Some(combined_path)
+            let candidates: Vec<PathBuf> = CONFIG_FILE_NAMES
+                .iter()
+                .map(|name| config_dir.join(name))
+                .collect();
+            let existing = candidates.iter().find(|path| path.exists());
+            Some(existing.unwrap_or(&candidates[0]).clone())
         }
     }
 }
@@ -102,6 +362,7 @@ mod test {
 
         let tag_to_save = Tag {
             paths: vec![tag_path1, tag_path2],
+            ..Tag::default()
         };
 
         let mut tags = HashMap::new();
@@ -110,7 +371,7 @@ mod test {
         let config_to_save = ConfigFile {
             version: crate::APP_VERSION.to_owned(),
             tags,
-            last_paths: None,
+            ..ConfigFile::new()
         };
 
         save_config_at(&test_config_path, &config_to_save)?;
@@ -120,4 +381,266 @@ mod test {
         dir.close()?;
         Ok(())
     }
+
+    #[test]
+    fn test_save_replaces_config_atomically() -> Result<()> {
+        let dir = tempdir::TempDir::new("mrttest")?;
+        let test_config_path = dir.path().join(".mrtconfig.json");
+
+        let mut config = ConfigFile::new();
+        save_config_at(&test_config_path, &config)?;
+        config.tags.insert(
+            String::from("testtag"),
+            Tag {
+                paths: vec![dir.path().to_path_buf()],
+                ..Tag::default()
+            },
+        );
+        save_config_at(&test_config_path, &config)?;
+
+        let files: Vec<PathBuf> = std::fs::read_dir(dir.path())?
+            .map(|entry| entry.map(|e| e.path()))
+            .collect::<std::io::Result<_>>()?;
+        assert_eq!(files, vec![test_config_path.clone()]);
+        assert_eq!(load_config(&test_config_path)?, config);
+
+        dir.close()?;
+        Ok(())
+    }
+
+    #[test]
+    fn test_old_config_is_migrated_with_backup() -> Result<()> {
+        let dir = tempdir::TempDir::new("mrttest")?;
+        let state_path = dir.path().join("state").join("state.json");
+        let test_config_path = dir.path().join("config.json");
+        let old_config = r#"{"version": "0.0.3", "tags": {}, "last_paths": ["/opt"]}"#;
+        std::fs::write(&test_config_path, old_config)?;
+
+        let migrated = load_raw_config_with_state(&test_config_path, Some(&state_path))?;
+        assert_eq!(migrated, ConfigFile::new());
+        assert_eq!(load_config(&test_config_path)?, ConfigFile::new());
+        assert_eq!(
+            std::fs::read_to_string(dir.path().join("config.json.v1.bak"))?,
+            old_config
+        );
+        assert_eq!(
+            state::loader::load_state(&state_path)?.last_paths,
+            vec![PathBuf::from("/opt")]
+        );
+
+        dir.close()?;
+        Ok(())
+    }
+
+    #[test]
+    fn test_config_is_migrated_in_memory_if_it_cant_be_saved() -> Result<()> {
+        let dir = tempdir::TempDir::new("mrttest")?;
+        let test_config_path = dir.path().join("config.json");
+        let old_config = r#"{"version": "0.0.3", "tags": {}}"#;
+        std::fs::write(&test_config_path, old_config)?;
+        // The backup can't be written over a directory, like the config can't be in a read-only store
+        std::fs::create_dir(dir.path().join("config.json.v1.bak"))?;
+
+        assert_eq!(load_config(&test_config_path)?, ConfigFile::new());
+        assert_eq!(std::fs::read_to_string(&test_config_path)?, old_config);
+
+        dir.close()?;
+        Ok(())
+    }
+
+    #[test]
+    fn test_read_write_toml_and_yaml() -> Result<()> {
+        let dir = tempdir::TempDir::new("mrttest")?;
+        let mut config = ConfigFile::new();
+        config.tags.insert(
+            String::from("backend"),
+            Tag {
+                paths: vec![dir.path().join("test1"), dir.path().join("test2")],
+                ..Tag::default()
+            },
+        );
+
+        for file_name in &["config.toml", "config.yaml"] {
+            let test_config_path = dir.path().join(file_name);
+            save_config_at(&test_config_path, &config)?;
+            assert_eq!(load_config(&test_config_path)?, config);
+        }
+
+        let commented = "# Shared config\nversion = \"0.0.3\"\nschema_version = 2\n\n[tags.backend]\npaths = [\"/opt/backend\"]\n";
+        let commented_path = dir.path().join("commented.toml");
+        std::fs::write(&commented_path, commented)?;
+        assert_eq!(
+            load_config(&commented_path)?.tags["backend"].paths,
+            vec![PathBuf::from("/opt/backend")]
+        );
+
+        dir.close()?;
+        Ok(())
+    }
+
+    #[test]
+    fn test_workspace_and_local_configs_are_merged_over_global() -> Result<()> {
+        let dir = tempdir::TempDir::new("mrttest")?;
+        let global_path = dir.path().join("global.json");
+        let workspace_dir = dir.path().join("workspace");
+        let nested_dir = workspace_dir.join("service-a").join("src");
+        std::fs::create_dir_all(&nested_dir)?;
+
+        let mut global = ConfigFile::new();
+        for tag_name in &["backend", "personal"] {
+            global.tags.insert(
+                String::from(*tag_name),
+                Tag {
+                    paths: vec![PathBuf::from("/opt/global")],
+                    ..Tag::default()
+                },
+            );
+        }
+        save_config_at(&global_path, &global)?;
+
+        std::fs::write(
+            workspace_dir.join(".mrt.toml"),
+            "[tags.backend]\npaths = [\"service-a\", \"/opt/service-b\"]\n\n[tags.frontend]\npaths = [\"web\"]\n",
+        )?;
+        std::fs::write(
+            workspace_dir.join(".mrt.local.toml"),
+            "[tags.frontend]\npaths = [\"../my-web\"]\n",
+        )?;
+
+        assert_eq!(
+            get_config_layers(&nested_dir),
+            vec![
+                workspace_dir.join(".mrt.toml"),
+                workspace_dir.join(".mrt.local.toml")
+            ]
+        );
+        assert!(get_config_layers(dir.path()).is_empty());
+
+        let config = load_layered_config(&global_path, &nested_dir)?;
+        assert_eq!(
+            config.tags["backend"].paths,
+            vec![
+                workspace_dir.join("service-a"),
+                PathBuf::from("/opt/service-b")
+            ]
+        );
+        assert_eq!(
+            config.tags["frontend"].paths,
+            vec![dir.path().join("my-web")]
+        );
+        assert_eq!(
+            config.tags["personal"].paths,
+            vec![PathBuf::from("/opt/global")]
+        );
+
+        dir.close()?;
+        Ok(())
+    }
+
+    #[test]
+    fn test_relative_paths_are_relative_to_config() -> Result<()> {
+        let dir = tempdir::TempDir::new("mrttest")?;
+        let config_path = dir.path().join("dotfiles").join("config.yaml");
+        std::fs::create_dir_all(dir.path().join("dotfiles"))?;
+        std::fs::write(
+            &config_path,
+            "tags:\n  backend:\n    paths:\n      - ../service-a\n      - /opt/service-b\n",
+        )?;
+
+        let config = load_config(&config_path)?;
+        assert_eq!(
+            config.tags["backend"].paths,
+            vec![
+                dir.path().join("service-a"),
+                PathBuf::from("/opt/service-b")
+            ]
+        );
+        assert_eq!(
+            load_raw_config(&config_path)?.tags["backend"].paths[0],
+            PathBuf::from("../service-a")
+        );
+
+        dir.close()?;
+        Ok(())
+    }
+
+    #[test]
+    fn test_unset_variable_in_config_is_kept_aside() -> Result<()> {
+        let dir = tempdir::TempDir::new("mrttest")?;
+        let config_path = dir.path().join("config.toml");
+        std::fs::write(
+            &config_path,
+            "schema_version = 2\nversion = \"0.0.3\"\n\n[tags.backend]\npaths = [\"$MRT_TEST_UNSET_WORKSPACE/service-a\", \"/opt/service-b\"]\n\n[tags.frontend]\npaths = [\"/opt/web\"]\n",
+        )?;
+
+        let config = load_config(&config_path)?;
+        assert_eq!(
+            config.tags["frontend"].paths,
+            vec![PathBuf::from("/opt/web")]
+        );
+        assert_eq!(
+            config.tags["backend"].paths,
+            vec![PathBuf::from("/opt/service-b")]
+        );
+        assert_eq!(
+            config.tags["backend"].unexpandable,
+            vec![(
+                PathBuf::from("$MRT_TEST_UNSET_WORKSPACE/service-a"),
+                format!(
+                    "Environment variable 'MRT_TEST_UNSET_WORKSPACE' used in '$MRT_TEST_UNSET_WORKSPACE/service-a' is not set, in '{}'",
+                    config_path.display()
+                )
+            )]
+        );
+
+        dir.close()?;
+        Ok(())
+    }
+
+    #[test]
+    fn test_profile_tags_are_added_when_selected() -> Result<()> {
+        let dir = tempdir::TempDir::new("mrttest")?;
+        let global_path = dir.path().join("global.toml");
+        std::fs::write(
+            &global_path,
+            "schema_version = 2\nversion = \"0.0.3\"\ndefault_tags = [\"shared\"]\n\n\
+             [tags.shared]\npaths = [\"/opt/shared\"]\n\n[tags.backend]\npaths = [\"/opt/backend\"]\n\n\
+             [profiles.work]\ndefault_tags = [\"backend\"]\n\n[profiles.work.tags.backend]\npaths = [\"work/api\"]\n",
+        )?;
+        std::fs::write(
+            dir.path().join(".mrt.toml"),
+            "[profiles.work.tags.frontend]\npaths = [\"/opt/work-web\"]\n",
+        )?;
+
+        let config = load_layered_config(&global_path, dir.path())?;
+        assert_eq!(config.default_tags, vec![String::from("shared")]);
+
+        let work = select_profile(config, "work")?;
+        assert_eq!(work.default_tags, vec![String::from("backend")]);
+        assert_eq!(
+            work.tags["backend"].paths,
+            vec![dir.path().join("work").join("api")]
+        );
+        assert_eq!(
+            work.tags["frontend"].paths,
+            vec![PathBuf::from("/opt/work-web")]
+        );
+        assert_eq!(
+            work.tags["shared"].paths,
+            vec![PathBuf::from("/opt/shared")]
+        );
+
+        let missing = select_profile(work, "home")
+            .map(|_| ())
+            .map_err(|e| e.to_string());
+        assert_eq!(
+            missing,
+            Err(String::from(
+                "Profile 'home' not found, there are no profiles in the config"
+            ))
+        );
+
+        dir.close()?;
+        Ok(())
+    }
 }
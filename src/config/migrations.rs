@@ -0,0 +1,108 @@
+use anyhow::{anyhow, Result};
+use serde_json::{Map, Value};
+use std::path::PathBuf;
+
+/// Version of the config format written by this version of mrt
+pub const CURRENT_SCHEMA_VERSION: u64 = 2;
+/// Configs written before versioning was introduced have no `schema_version`
+const UNVERSIONED_SCHEMA_VERSION: u64 = 1;
+
+type ConfigFields = Map<String, Value>;
+type Migration = fn(ConfigFields) -> ConfigFields;
+
+/// Migrations from a schema version to the next, the first one migrates from version 1
+const MIGRATIONS: &[Migration] = &[remove_last_paths];
+
+pub fn get_schema_version(config: &Value) -> u64 {
+    config
+        .get("schema_version")
+        .and_then(Value::as_u64)
+        .unwrap_or(UNVERSIONED_SCHEMA_VERSION)
+}
+
+/// Migrates a config from its schema version to the current one
+pub fn migrate(config: Value) -> Result<Value> {
+    let version = get_schema_version(&config);
+    if version > CURRENT_SCHEMA_VERSION {
+        return Err(anyhow!(
+            "Config has schema version {}, but this version of mrt only supports up to version {}. \
+             Please upgrade mrt.",
+            version,
+            CURRENT_SCHEMA_VERSION
+        ));
+    }
+
+    let mut fields = match config {
+        Value::Object(fields) => fields,
+        _ => return Err(anyhow!("Config should be an object")),
+    };
+
+    for migration in MIGRATIONS
+        .iter()
+        .skip((version - UNVERSIONED_SCHEMA_VERSION) as usize)
+    {
+        fields = migration(fields);
+    }
+
+    fields.insert(
+        String::from("schema_version"),
+        Value::from(CURRENT_SCHEMA_VERSION),
+    );
+    fields.insert(String::from("version"), Value::from(crate::APP_VERSION));
+    Ok(Value::Object(fields))
+}
+
+/// Previous paths stored in configs before version 2, so they can be moved to the state directory before migrating
+pub fn get_legacy_last_paths(config: &Value) -> Vec<PathBuf> {
+    config
+        .get("last_paths")
+        .and_then(|paths| serde_json::from_value(paths.clone()).ok())
+        .unwrap_or_default()
+}
+
+/// Previous paths were moved to the state directory in version 2
+fn remove_last_paths(mut config: ConfigFields) -> ConfigFields {
+    config.remove("last_paths");
+    config
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_migrate_unversioned_config() -> Result<()> {
+        let unversioned = json!({
+            "version": "0.0.1",
+            "tags": { "backend": { "paths": ["/opt/backend"] } },
+            "last_paths": ["/opt/backend"]
+        });
+
+        assert_eq!(
+            UNVERSIONED_SCHEMA_VERSION + MIGRATIONS.len() as u64,
+            CURRENT_SCHEMA_VERSION
+        );
+        assert_eq!(get_schema_version(&unversioned), 1);
+        assert_eq!(
+            migrate(unversioned)?,
+            json!({
+                "version": crate::APP_VERSION,
+                "schema_version": CURRENT_SCHEMA_VERSION,
+                "tags": { "backend": { "paths": ["/opt/backend"] } }
+            })
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_newer_config_is_rejected() {
+        let newer = json!({
+            "version": "99.0.0",
+            "schema_version": CURRENT_SCHEMA_VERSION + 1,
+            "tags": {}
+        });
+
+        assert!(migrate(newer).is_err());
+    }
+}
@@ -1,2 +1,4 @@
+pub mod format;
 pub mod loader;
+pub mod migrations;
 pub mod models;
@@ -1,3 +1,4 @@
+use super::migrations::CURRENT_SCHEMA_VERSION;
 use crate::APP_VERSION;
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
@@ -5,22 +6,47 @@ use std::path::PathBuf;
 
 #[derive(Serialize, Deserialize, Debug, Eq, PartialEq)]
 pub struct ConfigFile {
+    /// Version of mrt that wrote the config
     pub version: String,
+    /// Version of the config format, used to migrate configs written by older versions of mrt
+    pub schema_version: u64,
+    /// Tags used when none are given on the command line or in `MRT_DEFAULT_TAGS`
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub default_tags: Vec<String>,
+    #[serde(default)]
     pub tags: HashMap<String, Tag>,
-    pub last_paths: Option<Vec<PathBuf>>,
+    /// Named sets of tags selected with `--profile` or `MRT_PROFILE`
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+    pub profiles: HashMap<String, Profile>,
 }
 
 impl ConfigFile {
     pub fn new() -> Self {
         Self {
             version: String::from(APP_VERSION),
+            schema_version: CURRENT_SCHEMA_VERSION,
+            default_tags: vec![],
             tags: HashMap::new(),
-            last_paths: None,
+            profiles: HashMap::new(),
         }
     }
 }
 
-#[derive(Serialize, Deserialize, Debug, Eq, PartialEq)]
+#[derive(Serialize, Deserialize, Debug, Default, Eq, PartialEq)]
 pub struct Tag {
     pub paths: Vec<PathBuf>,
+    /// Paths that could not be expanded when loading the config, with the reason.
+    /// They are reported when the tag is used, so one unset variable doesn't stop mrt from loading the config
+    #[serde(skip)]
+    pub unexpandable: Vec<(PathBuf, String)>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Default, Eq, PartialEq)]
+pub struct Profile {
+    /// Replaces the top level default tags when the profile is selected
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub default_tags: Vec<String>,
+    /// Added to the top level tags when the profile is selected, replacing tags with the same name
+    #[serde(default)]
+    pub tags: HashMap<String, Tag>,
 }
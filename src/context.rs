@@ -0,0 +1,132 @@
+use crate::argparse::args::*;
+use crate::argparse::ParsedArgs;
+use crate::config::models::ConfigFile;
+use crate::execute::{self, PathExpansion, SelectedPaths};
+use crate::state;
+use anyhow::Result;
+use clap::ArgMatches;
+
+/// How subcommands should print their results
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum OutputMode {
+    Text,
+    /// Machine readable output, for use by other tools
+    Json,
+}
+
+/// Everything a subcommand or command needs from the arguments to mrt itself and the config,
+/// so selecting paths with tags, `-m` and `-L` works the same everywhere
+#[derive(Debug)]
+pub struct RunContext {
+    pub config: ConfigFile,
+    /// Tags and paths given on the command line, with the tag prefix, or the default tags
+    pub tags: Vec<String>,
+    pub output: OutputMode,
+    pub parallel: bool,
+    only_in_modified: bool,
+    use_last_paths: bool,
+    expansion: PathExpansion,
+}
+
+impl RunContext {
+    /// `args` are the matches of mrt itself, the arguments of the subcommand are matched separately
+    pub fn from_args(args: &ArgMatches, parsed_args: &ParsedArgs, config: ConfigFile) -> Self {
+        // Flags of mrt can't be given after the subcommand, except `--json`, which is global
+        let json = args.is_present(JSON_OUTPUT_ARG)
+            || args
+                .subcommand()
+                .1
+                .is_some_and(|matched| matched.is_present(JSON_OUTPUT_ARG));
+
+        Self {
+            config,
+            tags: parsed_args.tags.clone(),
+            output: if json {
+                OutputMode::Json
+            } else {
+                OutputMode::Text
+            },
+            parallel: args.is_present(PARALLEL_TAG),
+            only_in_modified: args.is_present(ONLY_IN_MODIFIED),
+            use_last_paths: args.is_present(USE_LAST_PATHS),
+            expansion: PathExpansion {
+                submodules: args.is_present(EXPAND_SUBMODULES),
+                worktrees: args.is_present(EXPAND_WORKTREES),
+            },
+        }
+    }
+
+    /// Paths selected by the tags, `-m`, `-L`, `--submodules` and `--worktrees`.
+    /// Paths are only resolved when asked for, since checking for modifications runs status in every path
+    pub fn select_paths(&self) -> Result<SelectedPaths> {
+        let last_paths = if self.use_last_paths {
+            state::loader::load_previous_paths()?
+        } else {
+            vec![]
+        };
+
+        if last_paths.is_empty() {
+            Ok(execute::get_all_paths(
+                &self.tags,
+                &self.config,
+                self.only_in_modified,
+                self.expansion,
+            ))
+        } else {
+            Ok(SelectedPaths {
+                paths: execute::existing_paths(&last_paths, "previous execution"),
+                missing: vec![],
+            })
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use clap::{App, Arg, SubCommand};
+
+    fn get_matches(args: &[&str]) -> ArgMatches<'static> {
+        App::new("mrt")
+            .arg(Arg::with_name(PARALLEL_TAG).short("p"))
+            .arg(
+                Arg::with_name(JSON_OUTPUT_ARG)
+                    .long(JSON_OUTPUT_ARG)
+                    .global(true),
+            )
+            .subcommand(SubCommand::with_name("status"))
+            .get_matches_from(args)
+    }
+
+    #[test]
+    fn test_context_from_args() {
+        let parsed_args = ParsedArgs {
+            tags: vec![String::from("+backend")],
+            before_tags: vec![],
+            after_tags: vec![],
+        };
+
+        let before = RunContext::from_args(
+            &get_matches(&["mrt", "-p", "--json", "status"]),
+            &parsed_args,
+            ConfigFile::new(),
+        );
+        let after = RunContext::from_args(
+            &get_matches(&["mrt", "status", "--json"]),
+            &parsed_args,
+            ConfigFile::new(),
+        );
+        let text = RunContext::from_args(
+            &get_matches(&["mrt", "status"]),
+            &parsed_args,
+            ConfigFile::new(),
+        );
+
+        assert_eq!(before.output, OutputMode::Json);
+        assert!(before.parallel);
+        assert_eq!(after.output, OutputMode::Json);
+        assert!(!after.parallel);
+        assert_eq!(text.output, OutputMode::Text);
+        assert_eq!(text.tags, vec![String::from("+backend")]);
+    }
+}
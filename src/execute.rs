@@ -1,31 +1,68 @@
 use super::argparse::ParsedArgs;
-use super::config::models::ConfigFile;
+use super::config::models::{ConfigFile, Tag};
 use super::util;
 use crate::argparse::args::*;
-use crate::config;
-use crate::subcommands::status::{get_num_dirty_files, run_status_command};
+use crate::context::RunContext;
+use crate::history;
+use crate::history::models::{ExecutionFlags, HistoryEntry, PathResult};
+use crate::state;
+use crate::vcs;
+use crate::vcs::git::{get_submodule_paths, get_worktree_paths};
 use anyhow::{anyhow, Result};
 use clap::ArgMatches;
 use colored::Colorize;
+use git2::Repository;
 use rayon::prelude::*;
+use std::collections::{BTreeMap, BTreeSet};
+use std::convert::TryFrom;
 use std::io::{BufRead, BufReader};
+use std::iter::once;
 use std::path::{Path, PathBuf};
-use std::process::{exit, Command, Stdio};
+use std::process::{Command, Stdio};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::{Duration, Instant};
 
 struct ExecutionOutput {
     exit_code: i32,
     stdout: String,
     stderr: String,
+    duration: Duration,
 }
 
-pub fn get_all_paths(tags: &[String], config: &ConfigFile, only_in_modified: bool) -> Vec<PathBuf> {
-    let mut all_paths: Vec<PathBuf> = if tags.is_empty() {
-        let nested_paths = config
+/// Nested repositories that selected paths should be expanded into, in addition to the paths themselves
+#[derive(Clone, Copy, Debug, Default)]
+pub struct PathExpansion {
+    pub submodules: bool,
+    pub worktrees: bool,
+}
+
+/// Paths selected by tags, split into the ones that exist and the ones that are missing
+#[derive(Debug, Default, Eq, PartialEq)]
+pub struct SelectedPaths {
+    pub paths: Vec<PathBuf>,
+    /// Configured paths that don't exist anymore, usually because they were moved or deleted
+    pub missing: Vec<PathBuf>,
+}
+
+pub fn get_all_paths(
+    tags: &[String],
+    config: &ConfigFile,
+    only_in_modified: bool,
+    expansion: PathExpansion,
+) -> SelectedPaths {
+    let sourced_paths: Vec<(String, PathBuf)> = if tags.is_empty() {
+        for (name, tag) in &config.tags {
+            warn_unexpandable(name, tag);
+        }
+        config
             .tags
             .iter()
-            .map(|(_tag_name, tag)| tag.paths.to_vec());
-
-        nested_paths.flatten().collect()
+            .flat_map(|(name, tag)| {
+                tag.paths
+                    .iter()
+                    .map(move |path| (format!("tag '{}'", name), path.clone()))
+            })
+            .collect()
     } else {
         tags.iter()
             .flat_map(|t| {
@@ -35,25 +72,35 @@ pub fn get_all_paths(tags: &[String], config: &ConfigFile, only_in_modified: boo
                 } else {
                     &t[1..]
                 };
-                match config.tags.get(tag_without_prefix) {
-                    Some(tag) => tag.paths.clone(),
-                    None => {
+                config.tags.get(tag_without_prefix).map_or_else(
+                    || {
                         let path = util::expand_path(tag_without_prefix);
                         if path.exists() {
-                            vec![path]
+                            vec![(String::from("command line"), path)]
                         } else {
                             println!("Tag or Path '{}' not found, skipping...", t);
                             vec![]
                         }
-                    }
-                }
+                    },
+                    |tag| {
+                        warn_unexpandable(tag_without_prefix, tag);
+                        let source = format!("tag '{}'", tag_without_prefix);
+                        tag.paths
+                            .iter()
+                            .map(|path| (source.clone(), path.clone()))
+                            .collect()
+                    },
+                )
             })
             .collect()
     };
 
+    let SelectedPaths { paths, missing } = split_missing(sourced_paths);
+    let mut all_paths = expand_paths(paths, expansion);
     all_paths.sort();
     all_paths.dedup();
-    if only_in_modified {
+
+    let paths = if only_in_modified {
         match get_modified_paths(all_paths.clone()) {
             Ok(ps) => ps,
             Err(e) => {
@@ -66,7 +113,84 @@ pub fn get_all_paths(tags: &[String], config: &ConfigFile, only_in_modified: boo
         }
     } else {
         all_paths
+    };
+
+    SelectedPaths { paths, missing }
+}
+
+/// Warns about the paths of a selected tag that could not be expanded when loading the config
+fn warn_unexpandable(tag_name: &str, tag: &Tag) {
+    for (path, error) in &tag.unexpandable {
+        eprintln!(
+            "{} '{}' from tag '{}' could not be expanded, skipping: {}",
+            "WARNING: Path".yellow(),
+            path.display(),
+            tag_name,
+            error
+        );
+    }
+}
+
+/// Splits paths into existing and missing ones, warning once about each missing path and where it came from
+fn split_missing(sourced_paths: Vec<(String, PathBuf)>) -> SelectedPaths {
+    let mut sources_by_path: BTreeMap<PathBuf, BTreeSet<String>> = BTreeMap::new();
+    for (source, path) in sourced_paths {
+        sources_by_path.entry(path).or_default().insert(source);
+    }
+
+    let mut selected = SelectedPaths::default();
+    for (path, sources) in sources_by_path {
+        if path.is_dir() {
+            selected.paths.push(path);
+            continue;
+        }
+
+        let problem = if path.exists() {
+            "is not a directory"
+        } else {
+            "does not exist"
+        };
+        eprintln!(
+            "{} '{}' from {} {}, skipping...",
+            "WARNING: Path".yellow(),
+            path.display(),
+            sources.into_iter().collect::<Vec<String>>().join(", "),
+            problem
+        );
+        selected.missing.push(path);
     }
+    selected
+}
+
+/// Keeps the paths that still exist, warning about the missing ones
+pub fn existing_paths(paths: &[PathBuf], source: &str) -> Vec<PathBuf> {
+    let sourced_paths = paths
+        .iter()
+        .map(|path| (String::from(source), path.clone()))
+        .collect();
+    split_missing(sourced_paths).paths
+}
+
+fn expand_paths(paths: Vec<PathBuf>, expansion: PathExpansion) -> Vec<PathBuf> {
+    if !expansion.submodules && !expansion.worktrees {
+        return paths;
+    }
+
+    paths
+        .into_iter()
+        .flat_map(|path| {
+            let mut nested = vec![];
+            if let Ok(repo) = Repository::open(&path) {
+                if expansion.submodules {
+                    nested.extend(get_submodule_paths(&repo));
+                }
+                if expansion.worktrees {
+                    nested.extend(get_worktree_paths(&repo));
+                }
+            }
+            once(path).chain(nested)
+        })
+        .collect()
 }
 
 fn get_modified_paths(paths: Vec<PathBuf>) -> Result<Vec<PathBuf>> {
@@ -85,12 +209,7 @@ fn get_modified_paths(paths: Vec<PathBuf>) -> Result<Vec<PathBuf>> {
 }
 
 fn is_modified(path: &Path) -> Result<bool> {
-    let output = run_status_command(path).output()?;
-    let output_string = String::from_utf8_lossy(&output.stdout).to_string();
-    let lines: Vec<String> = output_string.split('\n').map(String::from).collect();
-    let is_dirty = get_num_dirty_files(&lines) != 0;
-
-    Ok(is_dirty)
+    vcs::detect(path).is_modified(path)
 }
 
 fn get_headline(path: &Path) -> String {
@@ -120,57 +239,135 @@ fn print_result(path: &Path, output: &ExecutionOutput) {
     }
 }
 
-pub fn exec(clap_args: &ArgMatches, parsed_args: ParsedArgs, config: ConfigFile) -> Result<i32> {
+pub fn exec(clap_args: &ArgMatches, parsed_args: ParsedArgs, context: &RunContext) -> Result<i32> {
     let program = parsed_args.after_tags.first();
 
     match program {
         None => Err(anyhow!("Nothing to execute")),
         Some(prog) => {
             let args = &parsed_args.after_tags[1..];
+            let all_paths = context.select_paths()?.paths;
 
-            let only_in_modified = clap_args.is_present(ONLY_IN_MODIFIED);
-            let all_paths = if clap_args.is_present(USE_LAST_PATHS) {
-                match &config.last_paths {
-                    Some(last_paths) => last_paths.clone(),
-                    None => get_all_paths(&parsed_args.tags, &config, only_in_modified),
-                }
-            } else {
-                get_all_paths(&parsed_args.tags, &config, only_in_modified)
+            let flags = ExecutionFlags {
+                parallel: context.parallel,
+                continuous_output: clap_args.is_present(CONTINUOUS_OUTPUT_ARG),
+                shell: clap_args.is_present(SHELL_EXECUTION_ARG),
+                panic_on_nonzero: clap_args.is_present(PANIC_ON_NON_ZERO_ARG),
             };
 
-            let should_print_instantly = (!clap_args.is_present(PARALLEL_TAG))
-                || clap_args.is_present(CONTINUOUS_OUTPUT_ARG);
-
-            config::loader::store_previous_paths(config, &all_paths)?;
-
-            let execute_output = exec_all(
-                all_paths,
-                prog,
-                args,
-                clap_args.is_present(PARALLEL_TAG),
-                should_print_instantly,
-                clap_args.is_present(SHELL_EXECUTION_ARG),
-                clap_args.is_present(PANIC_ON_NON_ZERO_ARG),
-            )?;
-
-            execute_output
-                .into_iter()
-                .map(|(path, output)| {
-                    output.map(|res| {
-                        if !should_print_instantly {
-                            print_result(&path, &res);
-                        }
-                        path
-                    })
-                })
-                .collect::<Result<Vec<PathBuf>>>()
-                .map(|_| 0)
+            state::loader::store_previous_paths(&all_paths)?;
+
+            exec_and_record(all_paths, prog, args, &context.tags, flags)
         }
     }
 }
 
+/// Executes a previously recorded execution again, in the same paths and with the same flags
+pub fn rerun(entry: &HistoryEntry) -> Result<i32> {
+    let recorded_paths: Vec<PathBuf> = entry.results.iter().map(|r| r.path.clone()).collect();
+    let all_paths = existing_paths(&recorded_paths, &format!("history entry {}", entry.id));
+
+    match entry.command.split_first() {
+        None => Err(anyhow!("Nothing to execute")),
+        Some((prog, args)) => {
+            state::loader::store_previous_paths(&all_paths)?;
+            exec_and_record(all_paths, prog, args, &entry.selectors, entry.flags)
+        }
+    }
+}
+
+fn exec_and_record(
+    all_paths: Vec<PathBuf>,
+    prog: &str,
+    args: &[String],
+    selectors: &[String],
+    flags: ExecutionFlags,
+) -> Result<i32> {
+    let should_print_instantly = !flags.parallel || flags.continuous_output;
+
+    let execute_output = exec_all(
+        all_paths,
+        prog,
+        args,
+        flags.parallel,
+        should_print_instantly,
+        flags.shell,
+        flags.panic_on_nonzero,
+    )?;
+
+    record_history(prog, args, selectors, flags, &execute_output);
+
+    let any_failed = execute_output.iter().any(|(_, output)| {
+        output
+            .as_ref()
+            .is_some_and(|output| output.as_ref().is_ok_and(|res| res.exit_code != 0))
+    });
+
+    execute_output
+        .into_iter()
+        .filter_map(|(path, output)| output.map(|output| (path, output)))
+        .map(|(path, output)| {
+            output.map(|res| {
+                if !should_print_instantly {
+                    print_result(&path, &res);
+                }
+                path
+            })
+        })
+        .collect::<Result<Vec<PathBuf>>>()?;
+
+    if flags.panic_on_nonzero && any_failed {
+        eprintln!(
+            "\n\n{}",
+            "Encountered non-zero exit code, quitting...".red()
+        );
+        return Ok(1);
+    }
+    Ok(0)
+}
+
+/// Failing to record history should not fail the execution itself, so errors are only printed
+fn record_history(
+    prog: &str,
+    args: &[String],
+    selectors: &[String],
+    flags: ExecutionFlags,
+    execute_output: &[(PathBuf, Option<ExecuteResult>)],
+) {
+    let results = get_path_results(execute_output);
+    let mut command = vec![prog.to_owned()];
+    command.extend_from_slice(args);
+
+    if let Err(e) = history::loader::record(command, selectors.to_vec(), flags, results) {
+        eprintln!(
+            "{}\n{}",
+            "WARNING: Could not record execution in history...".yellow(),
+            e
+        );
+    }
+}
+
+/// Every selected path is recorded, also the ones skipped, so re-running the entry uses all of them
+fn get_path_results(execute_output: &[(PathBuf, Option<ExecuteResult>)]) -> Vec<PathResult> {
+    execute_output
+        .iter()
+        .map(|(path, output)| {
+            let executed = output.as_ref().and_then(|output| output.as_ref().ok());
+            PathResult {
+                path: path.clone(),
+                exit_code: executed.map(|o| o.exit_code),
+                duration_ms: executed.map_or(0, |o| {
+                    u64::try_from(o.duration.as_millis()).unwrap_or(u64::MAX)
+                }),
+                skipped: output.is_none(),
+            }
+        })
+        .collect()
+}
+
 type ExecuteResult = Result<ExecutionOutput>;
-type ExecuteResultForAllPaths = Result<Vec<(PathBuf, ExecuteResult)>>;
+/// The output in each path, `None` for paths skipped because of `-P`
+type ExecuteResultForAllPaths = Result<Vec<(PathBuf, Option<ExecuteResult>)>>;
 
 fn exec_all(
     all_paths: Vec<PathBuf>,
@@ -181,18 +378,24 @@ fn exec_all(
     execute_in_shell: bool,
     panic_on_nonzero_exitcode: bool,
 ) -> ExecuteResultForAllPaths {
+    // Set when a command exits with non-zero and `-P` is given, so paths not started yet are skipped
+    let stop = AtomicBool::new(false);
     let execute_func = |path: &PathBuf| {
-        (
-            path.clone(),
-            exec_at_path(
-                path,
-                prog.to_string(),
-                args,
-                should_print_instantly,
-                execute_in_shell,
-                panic_on_nonzero_exitcode,
-            ),
-        )
+        if stop.load(Ordering::SeqCst) {
+            return (path.clone(), None);
+        }
+
+        let output = exec_at_path(
+            path,
+            prog.to_string(),
+            args,
+            should_print_instantly,
+            execute_in_shell,
+        );
+        if panic_on_nonzero_exitcode && output.as_ref().is_ok_and(|res| res.exit_code != 0) {
+            stop.store(true, Ordering::SeqCst);
+        }
+        (path.clone(), Some(output))
     };
 
     if in_parallel {
@@ -212,7 +415,6 @@ fn exec_at_path(
     args: &[String],
     print: bool,
     execute_in_shell: bool,
-    panic_on_nonzero_exitcode: bool,
 ) -> ExecuteResult {
     let color_args = get_color_args(&command);
 
@@ -220,13 +422,13 @@ fn exec_at_path(
         if cfg!(target_os = "windows") {
             let powershell_command_arg = format!("{} {}", &command, args.join(" "));
             let mut powershell = Command::new("powershell");
-            powershell.args(&["/C", powershell_command_arg.as_str()]);
+            powershell.args(["/C", powershell_command_arg.as_str()]);
             powershell
         } else {
             let bash_command_arg =
                 format!("{} {} {}", &command, color_args.join(" "), args.join(" "));
             let mut bash = Command::new("bash");
-            bash.args(&["-c", bash_command_arg.as_str()]);
+            bash.args(["-c", bash_command_arg.as_str()]);
             bash
         }
     } else {
@@ -238,19 +440,13 @@ fn exec_at_path(
 
     cmd.current_dir(path);
 
-    let execution = if print {
+    let started = Instant::now();
+    let mut execution = if print {
         exec_with_connected_outputs(cmd, path)?
     } else {
         exec_with_captured_output(cmd)?
     };
-
-    if execution.exit_code != 0 && panic_on_nonzero_exitcode {
-        eprintln!(
-            "\n\n{}",
-            "Encountered non-zero exit code, quitting...".red()
-        );
-        exit(1)
-    }
+    execution.duration = started.elapsed();
 
     Ok(execution)
 }
@@ -294,6 +490,7 @@ fn exec_with_captured_output(mut cmd: Command) -> ExecuteResult {
         exit_code: code.code().unwrap_or(-1),
         stdout: stdout_l.join("\n"),
         stderr: stderr_l.join("\n"),
+        duration: Duration::default(),
     };
 
     Ok(exec_output)
@@ -312,6 +509,7 @@ fn exec_with_connected_outputs(mut cmd: Command, path: &Path) -> ExecuteResult {
         exit_code: waited.code().unwrap_or(-1),
         stdout: String::default(),
         stderr: String::default(),
+        duration: Duration::default(),
     };
     Ok(output)
 }
@@ -326,3 +524,82 @@ fn get_color_args(cmd_name: &str) -> Vec<&str> {
         vec![]
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_missing_paths_are_skipped() -> Result<()> {
+        let dir = tempdir::TempDir::new("mrttest")?;
+        let existing = dir.path().join("existing");
+        let moved = dir.path().join("moved");
+        std::fs::create_dir(&existing)?;
+
+        let file = dir.path().join("file");
+        std::fs::write(&file, "")?;
+
+        let mut config = ConfigFile::new();
+        config.tags.insert(
+            String::from("backend"),
+            Tag {
+                paths: vec![moved.clone(), existing.clone(), file.clone()],
+                ..Tag::default()
+            },
+        );
+        config.tags.insert(
+            String::from("services"),
+            Tag {
+                paths: vec![moved.clone()],
+                ..Tag::default()
+            },
+        );
+
+        let selected = get_all_paths(
+            &[String::from("+backend"), String::from("+services")],
+            &config,
+            false,
+            PathExpansion::default(),
+        );
+        assert_eq!(
+            selected,
+            SelectedPaths {
+                paths: vec![existing.clone()],
+                missing: vec![file, moved.clone()],
+            }
+        );
+        assert_eq!(
+            existing_paths(&[moved, existing.clone()], "test"),
+            vec![existing]
+        );
+
+        dir.close()?;
+        Ok(())
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_paths_after_nonzero_exit_code_are_skipped() -> Result<()> {
+        let dir = tempdir::TempDir::new("mrttest")?;
+        let paths = vec![dir.path().join("a"), dir.path().join("b")];
+        for path in &paths {
+            std::fs::create_dir(path)?;
+        }
+
+        let stopped = exec_all(paths.clone(), "false", &[], false, false, false, true)?;
+        let results = get_path_results(&stopped);
+        assert_eq!(
+            results
+                .iter()
+                .map(|r| (&r.path, r.exit_code, r.skipped))
+                .collect::<Vec<_>>(),
+            vec![(&paths[0], Some(1), false), (&paths[1], None, true)]
+        );
+
+        let continued = exec_all(paths, "false", &[], false, false, false, false)?;
+        assert!(get_path_results(&continued).iter().all(|r| !r.skipped));
+
+        dir.close()?;
+        Ok(())
+    }
+}
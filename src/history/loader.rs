@@ -0,0 +1,136 @@
+use super::models::*;
+
+use crate::state::loader::get_state_dir;
+use crate::util::{with_file_lock, write_file_atomically};
+use anyhow::{anyhow, Result};
+use std::fs::File;
+use std::io::prelude::*;
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+const HISTORY_ENV_NAME: &str = "MRT_HISTORY_PATH";
+const HISTORY_FILE_NAME: &str = "history.json";
+const MAX_HISTORY_ENTRIES: usize = 1000;
+
+pub fn load_history(path: &Path) -> Result<HistoryFile> {
+    if !path.exists() {
+        return Ok(HistoryFile::new());
+    }
+
+    let mut file = File::open(path)?;
+    let mut contents = String::new();
+    file.read_to_string(&mut contents)?;
+
+    let data: HistoryFile = serde_json::from_str(&contents)?;
+    Ok(data)
+}
+
+/** Appends an execution to the history file, returns the id it was recorded with */
+pub fn record(
+    command: Vec<String>,
+    selectors: Vec<String>,
+    flags: ExecutionFlags,
+    results: Vec<PathResult>,
+) -> Result<u64> {
+    let history_path =
+        get_history_path().ok_or_else(|| anyhow!("Could not detect correct history path"))?;
+    record_at(&history_path, command, selectors, flags, results)
+}
+
+fn record_at(
+    path: &Path,
+    command: Vec<String>,
+    selectors: Vec<String>,
+    flags: ExecutionFlags,
+    results: Vec<PathResult>,
+) -> Result<u64> {
+    with_file_lock(path, || {
+        let mut history = load_history(path)?;
+        let id = history.next_id();
+        let timestamp = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_secs())
+            .unwrap_or_default();
+
+        history.entries.push(HistoryEntry {
+            id,
+            timestamp,
+            command,
+            selectors,
+            flags,
+            results,
+        });
+
+        if history.entries.len() > MAX_HISTORY_ENTRIES {
+            let num_to_drop = history.entries.len() - MAX_HISTORY_ENTRIES;
+            history.entries.drain(..num_to_drop);
+        }
+
+        save_history_at(path, &history)?;
+        Ok(id)
+    })
+}
+
+fn save_history_at(path: &Path, history: &HistoryFile) -> Result<()> {
+    let data = serde_json::to_string(history)?;
+    write_file_atomically(path, data.as_bytes())
+}
+
+pub fn get_history_path() -> Option<PathBuf> {
+    match std::env::var(HISTORY_ENV_NAME) {
+        Ok(path) => Some(PathBuf::from(path)),
+        _ => Some(get_state_dir()?.join(HISTORY_FILE_NAME)),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_record_and_load_history() -> Result<()> {
+        let dir = tempdir::TempDir::new("mrttest")?;
+        // The directory of the history is created when recording the first execution
+        let history_path = dir.path().join("nested").join("history.json");
+
+        let results = vec![PathResult {
+            path: dir.path().join("repo"),
+            exit_code: Some(0),
+            duration_ms: 12,
+            skipped: false,
+        }];
+        let command = vec![String::from("git"), String::from("pull")];
+        let selectors = vec![String::from("+backend")];
+
+        let first_id = record_at(
+            &history_path,
+            command.clone(),
+            selectors.clone(),
+            ExecutionFlags::default(),
+            results.clone(),
+        )?;
+        let second_id = record_at(
+            &history_path,
+            command.clone(),
+            vec![],
+            ExecutionFlags::default(),
+            vec![],
+        )?;
+
+        let history = load_history(&history_path)?;
+
+        assert_eq!(first_id, 1);
+        assert_eq!(second_id, 2);
+        assert_eq!(history.entries.len(), 2);
+
+        let first = history
+            .get(first_id)
+            .expect("First entry should be recorded");
+        assert_eq!(first.command, command);
+        assert_eq!(first.selectors, selectors);
+        assert_eq!(first.results, results);
+
+        dir.close()?;
+        Ok(())
+    }
+}
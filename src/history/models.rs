@@ -0,0 +1,53 @@
+use serde::{Deserialize, Serialize};
+use std::path::PathBuf;
+
+#[derive(Serialize, Deserialize, Debug, Eq, PartialEq)]
+pub struct HistoryFile {
+    pub entries: Vec<HistoryEntry>,
+}
+
+impl HistoryFile {
+    pub const fn new() -> Self {
+        Self {
+            entries: Vec::new(),
+        }
+    }
+
+    pub fn next_id(&self) -> u64 {
+        self.entries.last().map_or(1, |entry| entry.id + 1)
+    }
+
+    pub fn get(&self, id: u64) -> Option<&HistoryEntry> {
+        self.entries.iter().find(|entry| entry.id == id)
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Eq, PartialEq)]
+pub struct HistoryEntry {
+    pub id: u64,
+    /// Seconds since unix epoch
+    pub timestamp: u64,
+    pub command: Vec<String>,
+    pub selectors: Vec<String>,
+    pub flags: ExecutionFlags,
+    pub results: Vec<PathResult>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, Default, Eq, PartialEq)]
+pub struct ExecutionFlags {
+    pub parallel: bool,
+    pub continuous_output: bool,
+    pub shell: bool,
+    pub panic_on_nonzero: bool,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Eq, PartialEq)]
+pub struct PathResult {
+    pub path: PathBuf,
+    /// `None` if the command could not be executed at all, or was skipped
+    pub exit_code: Option<i32>,
+    pub duration_ms: u64,
+    /// The command was not started, since it failed in an earlier path and `-P` was given
+    #[serde(default)]
+    pub skipped: bool,
+}
@@ -1,16 +1,22 @@
 #![deny(clippy::all, clippy::nursery, clippy::unwrap_used)]
 mod argparse;
 mod config;
+mod context;
 mod execute;
+mod history;
+mod state;
 mod subcommands;
+mod table;
 mod util;
+mod vcs;
 
 const APP_NAME: &str = "Multi Repo Tool";
 const APP_SHORT_NAME: &str = "mrt";
 const APP_VERSION: &str = "0.0.3";
 
+use crate::context::RunContext;
 use crate::subcommands::subcommand;
-use crate::subcommands::subcommand::MrtSubcommand;
+use crate::subcommands::subcommand::Subcommand;
 use anyhow::Result;
 use argparse::args::*;
 use clap::Arg;
@@ -56,6 +62,21 @@ fn help_text() -> String {
     {}
     {}
 
+    {}
+    {}
+
+    {}
+    {}
+
+    {}
+    {}
+
+    {}
+    {}
+
+    {}
+    {}
+
     {}
     {}
     ",
@@ -78,24 +99,35 @@ fn help_text() -> String {
         "$ mrt config -D backend",
         "# Removes all tags from current directory".bright_black(),
         "$ mrt config -r",
+        "# Tag other directories with tag `backend`".bright_black(),
+        "$ mrt config -a backend --path ../service-a --path ../service-b",
+        "# Rename tag `backend` to `api`, keeping its directories".bright_black(),
+        "$ mrt config --rename-tag backend api",
         "# Execute command in specified directory".bright_black(),
         "$ mrt +/opt/somedir ls -l",
         "# Execute command in dirty repositories".bright_black(),
         "$ mrt -m git diff",
         "# Launch a tmux session with a pane for each of the directories tagged with `backend`"
             .bright_black(),
-        "$ mrt +backend tmux"
+        "$ mrt +backend tmux",
+        "# Re-run the command with id `12` from the execution history".bright_black(),
+        "$ mrt history -r 12",
+        "# Prune missing directories from the config and normalise the remaining paths"
+            .bright_black(),
+        "$ mrt doctor --fix",
+        "# Run a command in the default tags of the 'work' profile in the config".bright_black(),
+        "$ mrt --profile work git pull"
     )
 }
 
 fn start_with_config(config: ConfigFile) -> Result<i32> {
-    let subcmds: Vec<MrtSubcommand> = subcommand::get_subcommands();
+    let subcmds: Vec<Box<dyn Subcommand>> = subcommand::get_subcommands();
     let parsed_arguments = argparse::parse_arguments(&subcmds);
 
     let args = clap::App::new(APP_NAME)
         .version(APP_VERSION)
-        .usage(format!("{} [FLAGS] [+tag ..] [--] [command]", APP_SHORT_NAME).as_ref())
-        .after_help(help_text().as_ref())
+        .usage(format!("{} [FLAGS] [+tag ..] [--] [command]", APP_SHORT_NAME).as_str())
+        .after_help(help_text().as_str())
         .arg(
             Arg::with_name(LIST_TAGS_ARG)
                 .short("l")
@@ -136,7 +168,7 @@ fn start_with_config(config: ConfigFile) -> Result<i32> {
                 .short("m")
                 .long(ONLY_IN_MODIFIED)
                 .multiple(false)
-                .help("Only execute command in modified repos (Modification detected by git-status).")
+                .help("Only execute command in modified repos (Modification detected by git or mercurial status).")
         )
         .arg(
             Arg::with_name(USE_LAST_PATHS)
@@ -145,11 +177,51 @@ fn start_with_config(config: ConfigFile) -> Result<i32> {
                 .multiple(false)
                 .help("Execute command in paths from previous execution of mrt.")
         )
-        .subcommands(subcmds.iter().map(|cmd| cmd.doc.to_owned()))
+        .arg(
+            Arg::with_name(EXPAND_SUBMODULES)
+                .long(EXPAND_SUBMODULES)
+                .multiple(false)
+                .help("Also execute command in the initialized submodules of each path.")
+        )
+        .arg(
+            Arg::with_name(EXPAND_WORKTREES)
+                .long(EXPAND_WORKTREES)
+                .multiple(false)
+                .help("Also execute command in the linked worktrees of each path.")
+        )
+        .arg(
+            Arg::with_name(PROFILE_ARG)
+                .long(PROFILE_ARG)
+                .value_name("PROFILE")
+                .env(argparse::PROFILE_ENV_VAR)
+                .help("Use the tags and default tags of a profile in the config, in addition to the tags outside of profiles.")
+        )
+        .arg(
+            Arg::with_name(JSON_OUTPUT_ARG)
+                .long(JSON_OUTPUT_ARG)
+                .global(true)
+                .help("Print the output of subcommands as json, for use by other tools. Can also be given after the subcommand.")
+        )
+        .subcommands(subcmds.iter().map(|cmd| cmd.doc()))
         .get_matches_from(&parsed_arguments.before_tags);
 
-    argparse::handle_args_to_self(subcmds, &args, &parsed_arguments, config)
-        .and_then(|c| execute::exec(&args, parsed_arguments, c))
+    let config = match args.value_of(PROFILE_ARG) {
+        Some(profile) => config::loader::select_profile(config, profile).unwrap_or_else(|e| {
+            eprintln!("{}\n{}", "ERROR: Could not select profile...".red(), e);
+            exit(2);
+        }),
+        None => config,
+    };
+    let parsed_arguments = parsed_arguments.or_default_tags(&config.default_tags);
+
+    argparse::handle_args_to_self(&args, &config);
+    let context = RunContext::from_args(&args, &parsed_arguments, config);
+    match args.subcommand() {
+        (name, Some(matched)) => subcommand::run_subcommand(&subcmds, name, matched, &context),
+        // Listing the tags is all `mrt -l` does when no command is given
+        _ if args.is_present(LIST_TAGS_ARG) && parsed_arguments.after_tags.is_empty() => Ok(0),
+        _ => execute::exec(&args, parsed_arguments, &context),
+    }
 }
 
 #[cfg(target_os = "windows")]
@@ -167,7 +239,8 @@ fn main() {
     configure_colored_crate();
 
     let config_path = get_config_path().unwrap_or_else(|| PathBuf::from(".mrtconfig.json"));
-    let config_to_use = match config::loader::load_config(config_path.as_path()) {
+    let current_dir = std::env::current_dir().unwrap_or_else(|_| PathBuf::from("."));
+    let config_to_use = match config::loader::load_layered_config(&config_path, &current_dir) {
         Ok(config) => config,
         Err(e) => {
             eprintln!("{}\n{}", "ERROR: Could not load config...".red(), e);
@@ -175,9 +248,19 @@ fn main() {
         }
     };
 
-    if let Ok(exit_code) = start_with_config(config_to_use) {
-        exit(exit_code)
-    } else {
-        exit(1)
+    match start_with_config(config_to_use) {
+        Ok(exit_code) => exit(exit_code),
+        Err(e) => {
+            print_error(&e);
+            exit(1)
+        }
+    }
+}
+
+/// Prints the error in red, followed by the errors that caused it
+fn print_error(error: &anyhow::Error) {
+    eprintln!("{}", format!("ERROR: {}", error).red());
+    for cause in error.chain().skip(1) {
+        eprintln!("{}", cause);
     }
 }
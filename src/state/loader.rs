@@ -0,0 +1,100 @@
+use super::models::*;
+
+use crate::util::{with_file_lock, write_file_atomically};
+use anyhow::{anyhow, Result};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+const STATE_DIR_ENV_NAME: &str = "MRT_STATE_DIR";
+const STATE_FILE_NAME: &str = "state.json";
+
+/// Directory for state like previous paths and history.
+/// Follows XDG on linux, and uses the local data directory on other platforms
+pub fn get_state_dir() -> Option<PathBuf> {
+    std::env::var(STATE_DIR_ENV_NAME).map_or_else(
+        |_| {
+            dirs::state_dir()
+                .or_else(dirs::data_local_dir)
+                .map(|dir| dir.join("mrt"))
+        },
+        |path| Some(PathBuf::from(path)),
+    )
+}
+
+pub fn get_state_path() -> Result<PathBuf> {
+    get_state_dir()
+        .map(|dir| dir.join(STATE_FILE_NAME))
+        .ok_or_else(|| anyhow!("Could not detect correct state path"))
+}
+
+pub fn load_state(path: &Path) -> Result<StateFile> {
+    if !path.exists() {
+        return Ok(StateFile::default());
+    }
+
+    let contents = fs::read_to_string(path)?;
+    Ok(serde_json::from_str(&contents)?)
+}
+
+pub fn load_previous_paths() -> Result<Vec<PathBuf>> {
+    Ok(load_state(&get_state_path()?)?.last_paths)
+}
+
+/// Stores previous paths kept in configs written by older versions of mrt, unless newer ones are stored already
+pub fn store_legacy_previous_paths_at(state_path: &Path, paths: &[PathBuf]) -> Result<()> {
+    if paths.is_empty() {
+        return Ok(());
+    }
+
+    update_state_at(state_path, |mut state| {
+        if state.last_paths.is_empty() {
+            state.last_paths = paths.to_owned();
+        }
+        state
+    })
+}
+
+pub fn store_previous_paths(paths: &[PathBuf]) -> Result<()> {
+    if paths.is_empty() {
+        return Ok(());
+    }
+
+    let state_path = get_state_path()?;
+    update_state_at(&state_path, |mut state| {
+        state.last_paths = paths.to_owned();
+        state
+    })
+}
+
+fn update_state_at<F>(path: &Path, modify: F) -> Result<()>
+where
+    F: FnOnce(StateFile) -> StateFile,
+{
+    with_file_lock(path, || {
+        let state = modify(load_state(path)?);
+        let data = serde_json::to_string_pretty(&state)?;
+        write_file_atomically(path, data.as_bytes())
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_update_state_creates_missing_dirs() -> Result<()> {
+        let dir = tempdir::TempDir::new("mrttest")?;
+        let state_path = dir.path().join("mrt").join(STATE_FILE_NAME);
+        assert_eq!(load_state(&state_path)?, StateFile::default());
+
+        let paths = vec![dir.path().join("repo1"), dir.path().join("repo2")];
+        update_state_at(&state_path, |mut state| {
+            state.last_paths = paths.clone();
+            state
+        })?;
+
+        assert_eq!(load_state(&state_path)?.last_paths, paths);
+        dir.close()?;
+        Ok(())
+    }
+}
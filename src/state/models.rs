@@ -0,0 +1,10 @@
+use serde::{Deserialize, Serialize};
+use std::path::PathBuf;
+
+/// Runtime state that mrt keeps between executions, separate from the user edited config
+#[derive(Serialize, Deserialize, Debug, Default, Eq, PartialEq)]
+pub struct StateFile {
+    /// Paths used by the previous execution, used by `--previous-paths`
+    #[serde(default)]
+    pub last_paths: Vec<PathBuf>,
+}
@@ -1,131 +1,486 @@
 use super::super::config;
 use super::super::config::models::*;
-use crate::argparse::ParsedArgs;
-use crate::subcommands::subcommand::MrtSubcommand;
-use anyhow::Result;
-use clap::{Arg, ArgMatches, SubCommand, Values};
+use crate::context::{OutputMode, RunContext};
+use crate::subcommands::subcommand::Subcommand;
+use crate::util;
+use anyhow::{anyhow, Context, Result};
+use clap::{App, Arg, ArgMatches, SubCommand};
+use colored::Colorize;
 use std::env;
+use std::path::{Path, PathBuf};
 
-pub fn get() -> MrtSubcommand {
-    MrtSubcommand {
-        name: String::from("config"),
-        run_subcommand: config,
-        doc: SubCommand::with_name("config")
-            .about("Subcommand to add and remove tags, generally configure mrt itself")
-            .arg(
-                Arg::with_name("add-tag")
-                    .short("a")
-                    .long("add-tag")
-                    .value_name("TAG_NAME")
-                    .multiple(true)
-                    .help("Tags the current directory with the specified tag"),
-            )
-            .arg(
-                Arg::with_name("del-tag")
-                    .short("d")
-                    .long("del-tag")
-                    .value_name("TAG_NAME")
-                    .multiple(true)
-                    .help("Untags the current directory from the specified tag"),
-            )
-            .arg(
-                Arg::with_name("del-current")
-                    .short("r")
-                    .long("del-current")
-                    .multiple(false)
-                    .help("Untags the current directory from all tags"),
-            )
-            .arg(
-                Arg::with_name("del-entire-tag")
-                    .short("D")
-                    .long("del-entire-tag")
-                    .value_name("TAG_NAME")
-                    .multiple(true)
-                    .help("Untags all directories of the specified tag and removes it entirely"),
-            ),
+/// Arguments that change the config, the others only read it
+const MODIFYING_ARGS: &[&str] = &[
+    "add-tag",
+    "del-tag",
+    "del-current",
+    "del-entire-tag",
+    "rename-tag",
+    "copy-tag",
+];
+
+pub struct Config;
+
+impl Subcommand for Config {
+    fn name(&self) -> &'static str {
+        "config"
+    }
+
+    fn doc(&self) -> App<'static, 'static> {
+        SubCommand::with_name(self.name())
+        .about("Subcommand to add and remove tags, generally configure mrt itself")
+        .arg(
+            Arg::with_name("add-tag")
+                .short("a")
+                .long("add-tag")
+                .value_name("TAG_NAME")
+                .multiple(true)
+                .help("Tags the current directory, or the directories given with --path, with the specified tag"),
+        )
+        .arg(
+            Arg::with_name("relative")
+                .long("relative")
+                .requires("add-tag")
+                .help("Stores the directory relative to the config file, so the config can be shared across machines"),
+        )
+        .arg(
+            Arg::with_name("del-tag")
+                .short("d")
+                .long("del-tag")
+                .value_name("TAG_NAME")
+                .multiple(true)
+                .help("Untags the current directory, or the directories given with --path, from the specified tag"),
+        )
+        .arg(
+            Arg::with_name("del-current")
+                .short("r")
+                .long("del-current")
+                .multiple(false)
+                .help("Untags the current directory, or the directories given with --path, from all tags"),
+        )
+        .arg(
+            Arg::with_name("del-entire-tag")
+                .short("D")
+                .long("del-entire-tag")
+                .value_name("TAG_NAME")
+                .multiple(true)
+                .help("Untags all directories of the specified tag and removes it entirely"),
+        )
+        .arg(
+            Arg::with_name("path")
+                .long("path")
+                .value_name("PATH")
+                .multiple(true)
+                .number_of_values(1)
+                .help("Directory to tag, untag or list tags for instead of the current directory, can be given several times"),
+        )
+        .arg(
+            Arg::with_name("rename-tag")
+                .long("rename-tag")
+                .value_names(&["OLD_NAME", "NEW_NAME"])
+                .help("Renames a tag, keeping its directories"),
+        )
+        .arg(
+            Arg::with_name("copy-tag")
+                .long("copy-tag")
+                .value_names(&["TAG_NAME", "NEW_NAME"])
+                .help("Creates a new tag with the same directories as an existing tag"),
+        )
+        .arg(
+            Arg::with_name("list")
+                .short("l")
+                .long("list")
+                .conflicts_with_all(MODIFYING_ARGS)
+                .help("Lists all tags, or the tags of the directories given with --path"),
+        )
+        .arg(
+            Arg::with_name("show")
+                .short("s")
+                .long("show")
+                .value_name("TAG_NAME")
+                .conflicts_with_all(MODIFYING_ARGS)
+                .conflicts_with("list")
+                .help("Shows the directories of the specified tag"),
+        )
     }
-}
 
-fn config(args: &ArgMatches, _parsed_args: &ParsedArgs, config: ConfigFile) {
-    let after_add_tag = match args.values_of("add-tag") {
-        Some(tags) => add_tag_to_current_dir(tags, config),
-        None => Ok(config),
-    };
+    fn run(&self, args: &ArgMatches, context: &RunContext) -> Result<i32> {
+        run_config(args, &context.config, context.output).context("Could not configure mrt...")?;
+        Ok(0)
+    }
 
-    let after_del_tag = after_add_tag.and_then(|conf| match args.values_of("del-tag") {
-        Some(tags) => remove_tag_from_current_dir(tags, conf),
-        None => Ok(conf),
-    });
+    fn supports_json(&self) -> bool {
+        true
+    }
+}
 
-    let after_del_entire = after_del_tag.and_then(|conf| match args.values_of("del-entire-tag") {
-        Some(tags) => delete_entire_tag(tags, conf),
-        None => Ok(conf),
-    });
+fn run_config(args: &ArgMatches, loaded_config: &ConfigFile, output: OutputMode) -> Result<()> {
+    let dirs = get_target_dirs(args)?;
 
-    let _after_del_current = after_del_entire.and_then(|conf| {
-        if args.is_present("del-current") {
-            del_current_dir(conf)
+    if args.is_present("list") {
+        if args.is_present("path") {
+            list_tags_of_dirs(loaded_config, &dirs, output)?;
         } else {
-            Ok(conf)
+            list_tags(loaded_config, output)?;
         }
-    });
+        return Ok(());
+    }
+
+    if let Some(tag) = args.value_of("show") {
+        return show_tag(loaded_config, tag, output);
+    }
+
+    if output == OutputMode::Json {
+        return Err(anyhow!("--json is only supported with --list and --show"));
+    }
+
+    if !MODIFYING_ARGS.iter().any(|arg| args.is_present(arg)) {
+        return Ok(());
+    }
+
+    // The config is read again while locked, so changes from concurrent runs of mrt are kept
+    config::loader::update_config(|config, config_path| {
+        let after_add_tag = match args.values_of("add-tag") {
+            Some(tags) => add_tag_to_dirs(
+                &tags.collect::<Vec<&str>>(),
+                &dirs,
+                config,
+                config_path,
+                args.is_present("relative"),
+            ),
+            None => Ok(config),
+        };
+
+        let after_del_tag = after_add_tag.and_then(|conf| match args.values_of("del-tag") {
+            Some(tags) => {
+                remove_tag_from_dirs(&tags.collect::<Vec<&str>>(), &dirs, conf, config_path)
+            }
+            None => Ok(conf),
+        });
+
+        let after_del_entire =
+            after_del_tag.and_then(|conf| match args.values_of("del-entire-tag") {
+                Some(tags) => delete_entire_tag(&tags.collect::<Vec<&str>>(), conf),
+                None => Ok(conf),
+            });
+
+        let after_del_current = after_del_entire.and_then(|conf| {
+            if args.is_present("del-current") {
+                untag_dirs(&dirs, conf, config_path)
+            } else {
+                Ok(conf)
+            }
+        });
+
+        let after_rename = after_del_current.and_then(|conf| match args.values_of("rename-tag") {
+            Some(mut names) => match (names.next(), names.next()) {
+                (Some(old_name), Some(new_name)) => rename_tag(old_name, new_name, conf),
+                _ => Ok(conf),
+            },
+            None => Ok(conf),
+        });
+
+        after_rename.and_then(|conf| match args.values_of("copy-tag") {
+            Some(mut names) => match (names.next(), names.next()) {
+                (Some(tag), Some(new_name)) => copy_tag(tag, new_name, conf),
+                _ => Ok(conf),
+            },
+            None => Ok(conf),
+        })
+    })?;
+    Ok(())
+}
+
+/// The directories given with `--path`, or the current directory if there are none
+fn get_target_dirs(args: &ArgMatches) -> Result<Vec<PathBuf>> {
+    let current_dir = env::current_dir()?;
+    Ok(match args.values_of("path") {
+        Some(paths) => paths
+            .map(|p| util::normalize_path(&current_dir.join(util::expand_path(p))))
+            .collect(),
+        None => vec![current_dir],
+    })
 }
 
-fn del_current_dir(mut config: ConfigFile) -> Result<ConfigFile> {
-    let current_path = env::current_dir()?;
+/// Whether a path as it is stored in the config points to `dir`
+fn is_stored_path_of(config_path: &Path, stored_path: &Path, dir: &Path) -> bool {
+    config::loader::resolve_config_path(config_path, stored_path).is_ok_and(|path| path == dir)
+}
 
+fn untag_dirs(dirs: &[PathBuf], mut config: ConfigFile, config_path: &Path) -> Result<ConfigFile> {
     let keys_to_iterate: Vec<String> = config.tags.keys().cloned().collect();
 
     for tag_name in keys_to_iterate {
         if let Some(t) = config.tags.get_mut(&tag_name) {
-            t.paths.retain(|path| *path != current_path);
+            t.paths.retain(|path| {
+                !dirs
+                    .iter()
+                    .any(|dir| is_stored_path_of(config_path, path, dir))
+            });
             if t.paths.is_empty() {
                 config.tags.remove(&tag_name);
             };
         }
     }
 
-    config::loader::save_config(config)
+    Ok(config)
 }
 
-fn delete_entire_tag(tags: Values, mut config: ConfigFile) -> Result<ConfigFile> {
+fn delete_entire_tag(tags: &[&str], mut config: ConfigFile) -> Result<ConfigFile> {
     for tag in tags {
         println!("Removing: {:#?}", tag);
-        config.tags.remove(tag);
+        config.tags.remove(*tag);
     }
-    config::loader::save_config(config)
+    Ok(config)
 }
 
-fn add_tag_to_current_dir(tags: Values, mut config: ConfigFile) -> Result<ConfigFile> {
+fn add_tag_to_dirs(
+    tags: &[&str],
+    dirs: &[PathBuf],
+    mut config: ConfigFile,
+    config_path: &Path,
+    relative: bool,
+) -> Result<ConfigFile> {
+    if let Some(missing) = dirs.iter().find(|dir| !dir.is_dir()) {
+        return Err(anyhow!("'{}' is not a directory", missing.display()));
+    }
+
     for tag in tags {
-        let current_path = env::current_dir()?;
+        let inserted_tag = config.tags.entry(tag.to_string()).or_default();
 
-        let inserted_tag = config
-            .tags
-            .entry(tag.to_string())
-            .or_insert(Tag { paths: vec![] });
-        inserted_tag.paths.push(current_path);
+        for dir in dirs {
+            let path_to_store = if relative {
+                util::relative_path(dir, &config::loader::get_config_dir(config_path))
+            } else {
+                dir.clone()
+            };
+
+            // Replaces the directory if it is already tagged, so it is stored the way it was asked for
+            inserted_tag
+                .paths
+                .retain(|path| !is_stored_path_of(config_path, path, dir));
+            inserted_tag.paths.push(path_to_store);
+        }
         inserted_tag.paths.sort();
-        inserted_tag.paths.dedup();
     }
-    config::loader::save_config(config)
+    Ok(config)
 }
 
-fn remove_tag_from_current_dir(tags: Values, mut config: ConfigFile) -> Result<ConfigFile> {
+fn remove_tag_from_dirs(
+    tags: &[&str],
+    dirs: &[PathBuf],
+    mut config: ConfigFile,
+    config_path: &Path,
+) -> Result<ConfigFile> {
     for tag in tags {
-        let current_path = env::current_dir()?;
-        let tag_to_remove_path_from = config.tags.get_mut(tag);
+        let tag_to_remove_path_from = config.tags.get_mut(*tag);
 
         match tag_to_remove_path_from {
             Some(tag_to_mod) => {
-                tag_to_mod.paths.retain(|path| *path != current_path);
+                tag_to_mod.paths.retain(|path| {
+                    !dirs
+                        .iter()
+                        .any(|dir| is_stored_path_of(config_path, path, dir))
+                });
 
                 if tag_to_mod.paths.is_empty() {
-                    config.tags.remove(tag);
+                    config.tags.remove(*tag);
                 };
             }
             _ => println!("Didn't exist as tag /shrug"),
         }
     }
-    config::loader::save_config(config)
+    Ok(config)
+}
+
+fn rename_tag(old_name: &str, new_name: &str, mut config: ConfigFile) -> Result<ConfigFile> {
+    if config.tags.contains_key(new_name) {
+        return Err(anyhow!("Tag '{}' already exists", new_name));
+    }
+    let tag = config
+        .tags
+        .remove(old_name)
+        .ok_or_else(|| anyhow!("Tag '{}' not found in config", old_name))?;
+
+    config.tags.insert(new_name.to_string(), tag);
+    Ok(config)
+}
+
+fn copy_tag(tag_name: &str, new_name: &str, mut config: ConfigFile) -> Result<ConfigFile> {
+    if config.tags.contains_key(new_name) {
+        return Err(anyhow!("Tag '{}' already exists", new_name));
+    }
+    let paths = config
+        .tags
+        .get(tag_name)
+        .map(|tag| tag.paths.clone())
+        .ok_or_else(|| anyhow!("Tag '{}' not found in config", tag_name))?;
+
+    config.tags.insert(
+        new_name.to_string(),
+        Tag {
+            paths,
+            ..Tag::default()
+        },
+    );
+    Ok(config)
+}
+
+fn list_tags(config: &ConfigFile, output: OutputMode) -> Result<()> {
+    let mut tag_names: Vec<&String> = config.tags.keys().collect();
+    tag_names.sort();
+
+    if output == OutputMode::Json {
+        let tags: Vec<serde_json::Value> = tag_names
+            .iter()
+            .map(|tag_name| tag_to_json(tag_name, &config.tags[*tag_name]))
+            .collect();
+        println!("{}", serde_json::to_string_pretty(&tags)?);
+        return Ok(());
+    }
+
+    for tag_name in tag_names {
+        let tag = &config.tags[tag_name];
+        let num_paths = tag.paths.len() + tag.unexpandable.len();
+        let paths_text = if num_paths == 1 { "path" } else { "paths" };
+        println!(
+            "{} {}",
+            tag_name,
+            format!("({} {})", num_paths, paths_text).bright_black()
+        );
+    }
+    Ok(())
+}
+
+fn list_tags_of_dirs(config: &ConfigFile, dirs: &[PathBuf], output: OutputMode) -> Result<()> {
+    if output == OutputMode::Json {
+        let dirs_with_tags: Vec<serde_json::Value> = dirs
+            .iter()
+            .map(|dir| serde_json::json!({ "path": dir, "tags": get_tags_of_dir(config, dir) }))
+            .collect();
+        println!("{}", serde_json::to_string_pretty(&dirs_with_tags)?);
+        return Ok(());
+    }
+
+    for dir in dirs {
+        let tag_names = get_tags_of_dir(config, dir);
+        let tags_text = if tag_names.is_empty() {
+            String::from("No tags").bright_black().to_string()
+        } else {
+            tag_names.join(", ")
+        };
+        println!("{}: {}", util::format_path(dir), tags_text);
+    }
+    Ok(())
+}
+
+/// Names of the tags that include `dir`, sorted
+fn get_tags_of_dir<'a>(config: &'a ConfigFile, dir: &Path) -> Vec<&'a str> {
+    let mut tag_names: Vec<&str> = config
+        .tags
+        .iter()
+        .filter(|(_, tag)| tag.paths.iter().any(|path| path == dir))
+        .map(|(tag_name, _)| tag_name.as_str())
+        .collect();
+    tag_names.sort_unstable();
+    tag_names
+}
+
+fn show_tag(config: &ConfigFile, tag_name: &str, output: OutputMode) -> Result<()> {
+    let tag = config
+        .tags
+        .get(tag_name)
+        .ok_or_else(|| anyhow!("Tag '{}' not found in config", tag_name))?;
+
+    if output == OutputMode::Json {
+        println!(
+            "{}",
+            serde_json::to_string_pretty(&tag_to_json(tag_name, tag))?
+        );
+        return Ok(());
+    }
+
+    println!("{}:", tag_name);
+    for path in &tag.paths {
+        println!("  {}", util::format_path(path));
+    }
+    for (path, error) in &tag.unexpandable {
+        println!(
+            "  {} {}",
+            path.display(),
+            format!("(could not be expanded: {})", error).yellow()
+        );
+    }
+    Ok(())
+}
+
+/// Paths that could not be expanded are listed as written in the config
+fn tag_to_json(tag_name: &str, tag: &Tag) -> serde_json::Value {
+    let unexpandable: Vec<&PathBuf> = tag.unexpandable.iter().map(|(path, _)| path).collect();
+    serde_json::json!({
+        "name": tag_name,
+        "paths": tag.paths,
+        "unexpandable": unexpandable,
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_tag_paths_and_rename_and_copy_tags() -> Result<()> {
+        let dir = tempdir::TempDir::new("mrttest")?;
+        let config_path = dir.path().join("config.json");
+        let service_a = dir.path().join("service-a");
+        let service_b = dir.path().join("service-b");
+        std::fs::create_dir(&service_a)?;
+        std::fs::create_dir(&service_b)?;
+
+        let both = vec![service_a.clone(), service_b.clone()];
+        let config = add_tag_to_dirs(&["backend"], &both, ConfigFile::new(), &config_path, true)?;
+        assert_eq!(
+            config.tags["backend"].paths,
+            vec![PathBuf::from("service-a"), PathBuf::from("service-b")]
+        );
+        let missing = vec![dir.path().join("missing")];
+        assert!(add_tag_to_dirs(
+            &["backend"],
+            &missing,
+            ConfigFile::new(),
+            &config_path,
+            false
+        )
+        .is_err());
+
+        let config = copy_tag("backend", "services", config)?;
+        let config = rename_tag("backend", "api", config)?;
+        assert!(!config.tags.contains_key("backend"));
+        assert_eq!(config.tags["api"], config.tags["services"]);
+        assert!(copy_tag("backend", "other", ConfigFile::new()).is_err());
+
+        let config = remove_tag_from_dirs(
+            &["api"],
+            std::slice::from_ref(&service_a),
+            config,
+            &config_path,
+        )?;
+        assert_eq!(config.tags["api"].paths, vec![PathBuf::from("service-b")]);
+        let config = untag_dirs(&[service_b], config, &config_path)?;
+        assert!(!config.tags.contains_key("api"));
+        assert!(rename_tag("services", "services", config).is_err());
+
+        let mut loaded = ConfigFile::new();
+        loaded.tags.insert(
+            String::from("backend"),
+            Tag {
+                paths: vec![service_a.clone()],
+                ..Tag::default()
+            },
+        );
+        assert_eq!(get_tags_of_dir(&loaded, &service_a), vec!["backend"]);
+
+        dir.close()?;
+        Ok(())
+    }
 }
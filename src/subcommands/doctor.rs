@@ -0,0 +1,511 @@
+use super::subcommand::Subcommand;
+use crate::config;
+use crate::config::models::{ConfigFile, Profile, Tag};
+use crate::context::{OutputMode, RunContext};
+use crate::util;
+use crate::vcs;
+use crate::vcs::git::get_unreachable_local_remotes;
+use anyhow::{Context, Result};
+use clap::{App, Arg, ArgMatches, SubCommand};
+use colored::Colorize;
+use git2::Repository;
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::env;
+use std::path::{Component, Path, PathBuf};
+
+pub struct Doctor;
+
+impl Subcommand for Doctor {
+    fn name(&self) -> &'static str {
+        "doctor"
+    }
+
+    fn doc(&self) -> App<'static, 'static> {
+        SubCommand::with_name(self.name())
+            .about("Checks the config for missing, duplicated and unexpandable paths")
+            .arg(Arg::with_name("fix").short("f").long("fix").help(
+                "Prunes missing paths from the config in the home directory and stores the rest normalised and listed once per tag",
+            ))
+    }
+
+    fn run(&self, args: &ArgMatches, context: &RunContext) -> Result<i32> {
+        run_doctor(args, context.output).context("Could not check config...")?;
+        Ok(0)
+    }
+
+    fn supports_json(&self) -> bool {
+        true
+    }
+}
+
+#[derive(Debug, Eq, PartialEq)]
+enum Problem {
+    Missing,
+    /// The directory is listed more than once in the same tag
+    Duplicate,
+    /// The directory is listed with another spelling, in the same or another tag
+    SpelledDifferently(PathBuf),
+    /// The directory is listed the same way in another tag
+    AlsoInTag(String),
+    NotRepository,
+    UnreachableRemote {
+        name: String,
+        url: String,
+    },
+    /// The path uses an environment variable that is not set
+    NotExpandable(String),
+}
+
+impl Problem {
+    fn description(&self) -> String {
+        match self {
+            Self::Missing => String::from("does not exist"),
+            Self::Duplicate => String::from("is listed more than once"),
+            Self::SpelledDifferently(other) => format!("is also listed as '{}'", other.display()),
+            Self::AlsoInTag(other) => format!("is also listed in tag '{}'", other),
+            Self::NotRepository => String::from("is not a repository"),
+            Self::UnreachableRemote { name, url } => {
+                format!("has remote '{}' pointing to missing '{}'", name, url)
+            }
+            Self::NotExpandable(error) => format!("could not be expanded: {}", error),
+        }
+    }
+
+    /// Whether the problem is resolved by `--fix`
+    const fn is_fixable(&self) -> bool {
+        matches!(
+            self,
+            Self::Missing | Self::Duplicate | Self::SpelledDifferently(_)
+        )
+    }
+}
+
+#[derive(Debug, Eq, PartialEq)]
+struct Finding {
+    /// `None` for tags outside of profiles
+    profile: Option<String>,
+    tag: String,
+    path: PathBuf,
+    problem: Problem,
+}
+
+/// Findings in one config file
+struct Report {
+    config_path: PathBuf,
+    findings: Vec<Finding>,
+    /// Whether `--fix` changes the file, workspace and local configs are shared so they are only checked
+    fixable: bool,
+}
+
+fn run_doctor(args: &ArgMatches, output: OutputMode) -> Result<()> {
+    let should_fix = args.is_present("fix");
+
+    // Paths are checked as they are stored, since loading the config expands them
+    let global_report = config::loader::with_config_lock(|config_path| {
+        if !config_path.exists() {
+            return Ok(None);
+        }
+        let raw_config = config::loader::load_raw_config(config_path)?;
+        let findings = check(&raw_config, config_path);
+        if should_fix && has_fixable(&findings) {
+            config::loader::save_config_at(config_path, &fix(raw_config, config_path))?;
+        }
+        Ok(Some(Report {
+            config_path: config_path.to_path_buf(),
+            findings,
+            fixable: true,
+        }))
+    })?;
+
+    let mut reports: Vec<Report> = global_report.into_iter().collect();
+    for layer_path in config::loader::get_config_layers(&env::current_dir()?) {
+        let raw_layer = config::loader::load_raw_config_layer(&layer_path)?;
+        reports.push(Report {
+            findings: check(&raw_layer, &layer_path),
+            config_path: layer_path,
+            fixable: false,
+        });
+    }
+
+    match output {
+        OutputMode::Json => {
+            let findings: Vec<serde_json::Value> = reports
+                .iter()
+                .flat_map(|report| {
+                    report
+                        .findings
+                        .iter()
+                        .map(move |finding| finding_to_json(report, finding, should_fix))
+                })
+                .collect();
+            println!("{}", serde_json::to_string_pretty(&findings)?);
+        }
+        OutputMode::Text => print_reports(&reports, should_fix),
+    }
+    Ok(())
+}
+
+fn print_reports(reports: &[Report], should_fix: bool) {
+    for report in reports {
+        let config_path = report.config_path.display();
+        if report.findings.is_empty() {
+            println!(
+                "{}",
+                format!("No problems found in {}", config_path).green()
+            );
+            continue;
+        }
+
+        let fixed = should_fix && report.fixable;
+        println!("{}:", config_path);
+        for finding in &report.findings {
+            print_finding(finding, fixed);
+        }
+        if fixed && has_fixable(&report.findings) {
+            println!("Fixed config at {}", config_path);
+        } else if !report.fixable && has_fixable(&report.findings) {
+            println!(
+                "{}",
+                "Workspace and local configs are shared, so they are not changed by --fix"
+                    .bright_black()
+            );
+        }
+    }
+
+    let any_fixable = reports
+        .iter()
+        .any(|report| report.fixable && has_fixable(&report.findings));
+    if !should_fix && any_fixable {
+        println!("\nRun `mrt doctor --fix` to prune or normalise the paths");
+    }
+}
+
+fn has_fixable(findings: &[Finding]) -> bool {
+    findings.iter().any(|f| f.problem.is_fixable())
+}
+
+fn print_finding(finding: &Finding, fixed: bool) {
+    let description = finding.problem.description();
+    let colored_description = if finding.problem == Problem::Missing {
+        description.red()
+    } else {
+        description.yellow()
+    };
+    let fix_text = if fixed && finding.problem.is_fixable() {
+        format!(" {}", "(fixed)".green())
+    } else {
+        String::default()
+    };
+
+    let tag_text = finding.profile.as_ref().map_or_else(
+        || format!("in tag '{}'", finding.tag),
+        |profile| format!("in tag '{}' of profile '{}'", finding.tag, profile),
+    );
+
+    println!(
+        "{} {} {}{}",
+        util::format_path(&finding.path),
+        tag_text.bright_black(),
+        colored_description,
+        fix_text
+    );
+}
+
+fn finding_to_json(report: &Report, finding: &Finding, should_fix: bool) -> serde_json::Value {
+    let fixable = report.fixable && finding.problem.is_fixable();
+    serde_json::json!({
+        "config": report.config_path,
+        "profile": finding.profile,
+        "tag": finding.tag,
+        "path": finding.path,
+        "problem": finding.problem.description(),
+        "fixable": fixable,
+        "fixed": should_fix && fixable,
+    })
+}
+
+fn check(config: &ConfigFile, config_path: &Path) -> Vec<Finding> {
+    let mut findings = check_tags(&config.tags, None, config_path);
+    let sorted_profiles: BTreeMap<&String, &Profile> = config.profiles.iter().collect();
+    for (profile_name, profile) in sorted_profiles {
+        findings.extend(check_tags(&profile.tags, Some(profile_name), config_path));
+    }
+    findings
+}
+
+/// The tags of each profile are checked on their own, paths are only compared within the same set of tags
+fn check_tags(
+    tags: &HashMap<String, Tag>,
+    profile: Option<&String>,
+    config_path: &Path,
+) -> Vec<Finding> {
+    let normalise = |path: &Path| normalise(config_path, path);
+    let sorted_tags: BTreeMap<&String, _> = tags.iter().collect();
+
+    // Normalised paths mapped to every spelling of them in the config, with the tag they are in
+    let mut spellings: HashMap<PathBuf, Vec<(&String, &Path)>> = HashMap::new();
+    for (tag_name, tag) in &sorted_tags {
+        for path in &tag.paths {
+            if let Ok(normalised) = normalise(path) {
+                spellings
+                    .entry(normalised)
+                    .or_default()
+                    .push((tag_name, path));
+            }
+        }
+    }
+
+    let mut findings = vec![];
+    for (tag_name, tag) in sorted_tags {
+        for (i, path) in tag.paths.iter().enumerate() {
+            let mut report = |problem| {
+                findings.push(Finding {
+                    profile: profile.cloned(),
+                    tag: tag_name.clone(),
+                    path: path.clone(),
+                    problem,
+                })
+            };
+            let normalised = match normalise(path) {
+                Ok(normalised) => normalised,
+                Err(e) => {
+                    report(Problem::NotExpandable(e.to_string()));
+                    continue;
+                }
+            };
+
+            let listed_twice_in_tag = tag.paths[..i]
+                .iter()
+                .any(|p| normalise(p).is_ok_and(|other| other == normalised));
+            let listings = spellings.get(&normalised).map_or(&[][..], Vec::as_slice);
+            let other_spelling = listings.iter().find(|(_, p)| *p != path.as_path());
+            let other_tag = listings.iter().find(|(other, _)| *other != tag_name);
+            if let Some((_, other)) = other_spelling {
+                report(Problem::SpelledDifferently(other.to_path_buf()));
+            } else if listed_twice_in_tag {
+                report(Problem::Duplicate);
+            } else if let Some((other, _)) = other_tag {
+                report(Problem::AlsoInTag((*other).clone()));
+            }
+
+            if !normalised.is_dir() {
+                report(Problem::Missing);
+                continue;
+            }
+
+            if vcs::detect(&normalised).name() == vcs::plain::NAME {
+                report(Problem::NotRepository);
+            }
+
+            if let Ok(repo) = Repository::open(&normalised) {
+                for (name, url) in get_unreachable_local_remotes(&repo) {
+                    report(Problem::UnreachableRemote { name, url });
+                }
+            }
+        }
+    }
+    findings
+}
+
+/// Prunes missing paths, normalises the rest and removes tags left without paths, also in profiles.
+/// Relative paths, `~` and environment variables are kept, since they are meant to differ between machines
+fn fix(mut config: ConfigFile, config_path: &Path) -> ConfigFile {
+    fix_tags(&mut config.tags, config_path);
+    for profile in config.profiles.values_mut() {
+        fix_tags(&mut profile.tags, config_path);
+    }
+    config
+}
+
+fn fix_tags(tags: &mut HashMap<String, Tag>, config_path: &Path) {
+    for tag in tags.values_mut() {
+        let mut seen = HashSet::new();
+        tag.paths = tag
+            .paths
+            .iter()
+            .filter(|p| seen.insert(normalise(config_path, p).unwrap_or_else(|_| p.to_path_buf())))
+            .filter(|p| normalise(config_path, p).map_or(true, |normalised| normalised.is_dir()))
+            .map(|p| normalise_stored(p))
+            .collect();
+        tag.paths.sort();
+    }
+    tags.retain(|_, tag| !tag.paths.is_empty());
+}
+
+/// Removes `.` and `..` from a path as it is stored. A `..` after `~` or a variable would remove
+/// the variable itself rather than a directory, so only `.` is removed from those
+fn normalise_stored(path: &Path) -> PathBuf {
+    if path.starts_with("~") || path.to_string_lossy().contains('$') {
+        path.components()
+            .filter(|component| *component != Component::CurDir)
+            .collect()
+    } else {
+        util::normalize_path(path)
+    }
+}
+
+/// Resolves `path` the same way mrt does when loading the config,
+/// and resolves symlinks when the path exists so different spellings of a directory are equal
+fn normalise(config_path: &Path, path: &Path) -> Result<PathBuf> {
+    let resolved = config::loader::resolve_config_path(config_path, path)?;
+    Ok(resolved.canonicalize().unwrap_or(resolved))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_check_and_fix_config() -> Result<()> {
+        let dir = tempdir::TempDir::new("mrttest")?;
+        let dir_path = dir.path().canonicalize()?;
+        let repo_path = dir_path.join("repo");
+        let plain_path = dir_path.join("plain");
+        Repository::init(&repo_path)?;
+        std::fs::create_dir(&plain_path)?;
+        let other_repo_spelling = plain_path.join("..").join("repo");
+        let config_path = dir_path.join("config.json");
+
+        let mut config = ConfigFile::new();
+        config.tags.insert(
+            String::from("backend"),
+            Tag {
+                paths: vec![
+                    repo_path.clone(),
+                    dir_path.join("missing"),
+                    dir_path.join("missing"),
+                ],
+                ..Tag::default()
+            },
+        );
+        config.tags.insert(
+            String::from("other"),
+            Tag {
+                paths: vec![PathBuf::from("plain"), other_repo_spelling.clone()],
+                ..Tag::default()
+            },
+        );
+
+        let findings = check(&config, &config_path);
+        let problems: Vec<(&str, &Problem)> = findings
+            .iter()
+            .map(|f| (f.tag.as_str(), &f.problem))
+            .collect();
+        assert_eq!(
+            problems,
+            vec![
+                ("backend", &Problem::SpelledDifferently(other_repo_spelling)),
+                ("backend", &Problem::Missing),
+                ("backend", &Problem::Duplicate),
+                ("backend", &Problem::Missing),
+                ("other", &Problem::NotRepository),
+                ("other", &Problem::SpelledDifferently(repo_path.clone())),
+            ]
+        );
+
+        // Relative paths are kept relative to the config file
+        let fixed = fix(config, &config_path);
+        assert_eq!(fixed.tags["backend"].paths, vec![repo_path.clone()]);
+        assert_eq!(
+            fixed.tags["other"].paths,
+            vec![repo_path, PathBuf::from("plain")]
+        );
+        assert_eq!(
+            check(&fixed, &config_path)
+                .into_iter()
+                .map(|f| (f.tag, f.problem))
+                .collect::<Vec<(String, Problem)>>(),
+            vec![
+                (
+                    String::from("backend"),
+                    Problem::AlsoInTag(String::from("other"))
+                ),
+                (
+                    String::from("other"),
+                    Problem::AlsoInTag(String::from("backend"))
+                ),
+                (String::from("other"), Problem::NotRepository),
+            ]
+        );
+
+        dir.close()?;
+        Ok(())
+    }
+
+    #[test]
+    fn test_home_paths_are_kept_as_written() -> Result<()> {
+        let dir = tempdir::TempDir::new("mrttest")?;
+        let config_path = dir.path().join("config.json");
+
+        let home_name = dirs::home_dir()
+            .and_then(|home| home.file_name().map(PathBuf::from))
+            .ok_or_else(|| anyhow::anyhow!("No home directory"))?;
+        let home_through_parent = Path::new("~/..").join(home_name);
+        let unset_variable = PathBuf::from("$MRT_TEST_UNSET_VARIABLE/./../common");
+
+        let mut config = ConfigFile::new();
+        config.tags.insert(
+            String::from("home"),
+            Tag {
+                paths: vec![PathBuf::from("~/./")],
+                ..Tag::default()
+            },
+        );
+        config.tags.insert(
+            String::from("parent"),
+            Tag {
+                paths: vec![home_through_parent.clone(), unset_variable],
+                ..Tag::default()
+            },
+        );
+
+        assert!(check(&config, &config_path)
+            .iter()
+            .all(|f| f.problem != Problem::Missing));
+        let fixed = fix(config, &config_path);
+        assert_eq!(fixed.tags["home"].paths, vec![PathBuf::from("~")]);
+        assert_eq!(
+            fixed.tags["parent"].paths,
+            vec![
+                PathBuf::from("$MRT_TEST_UNSET_VARIABLE/../common"),
+                home_through_parent
+            ]
+        );
+
+        dir.close()?;
+        Ok(())
+    }
+
+    #[test]
+    fn test_profile_tags_are_checked_and_fixed() -> Result<()> {
+        let dir = tempdir::TempDir::new("mrttest")?;
+        let config_path = dir.path().join("config.json");
+        let missing = dir.path().join("missing");
+
+        let mut profile = Profile::default();
+        profile.tags.insert(
+            String::from("api"),
+            Tag {
+                paths: vec![missing.clone()],
+                ..Tag::default()
+            },
+        );
+        let mut config = ConfigFile::new();
+        config.profiles.insert(String::from("work"), profile);
+
+        assert_eq!(
+            check(&config, &config_path),
+            vec![Finding {
+                profile: Some(String::from("work")),
+                tag: String::from("api"),
+                path: missing,
+                problem: Problem::Missing,
+            }]
+        );
+        let fixed = fix(config, &config_path);
+        assert!(fixed.profiles["work"].tags.is_empty());
+
+        dir.close()?;
+        Ok(())
+    }
+}
@@ -0,0 +1,218 @@
+use super::subcommand::Subcommand;
+use crate::context::{OutputMode, RunContext};
+use crate::execute;
+use crate::history::loader::{get_history_path, load_history};
+use crate::history::models::{HistoryEntry, HistoryFile};
+use crate::util;
+use anyhow::{anyhow, Context, Result};
+use clap::{App, Arg, ArgMatches, SubCommand};
+use colored::Colorize;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+const DEFAULT_LIST_LIMIT: &str = "20";
+
+pub struct History;
+
+impl Subcommand for History {
+    fn name(&self) -> &'static str {
+        "history"
+    }
+
+    fn doc(&self) -> App<'static, 'static> {
+        SubCommand::with_name(self.name())
+        .about("List, inspect and re-run previous executions of mrt")
+        .arg(
+            Arg::with_name("show")
+                .short("s")
+                .long("show")
+                .value_name("ID")
+                .help("Shows exit code and duration for each path of the execution with the specified id"),
+        )
+        .arg(
+            Arg::with_name("rerun")
+                .short("r")
+                .long("rerun")
+                .value_name("ID")
+                .conflicts_with("show")
+                .help("Executes the command with the specified id again, in the same paths and with the same flags"),
+        )
+        .arg(
+            Arg::with_name("limit")
+                .short("n")
+                .long("limit")
+                .value_name("NUM")
+                .default_value(DEFAULT_LIST_LIMIT)
+                .help("Number of previous executions to list"),
+        )
+    }
+
+    fn run(&self, args: &ArgMatches, context: &RunContext) -> Result<i32> {
+        run_history(args, context.output).context("Could not handle history...")
+    }
+
+    fn supports_json(&self) -> bool {
+        true
+    }
+}
+
+/// Returns the exit code of the re-run command, if any
+fn run_history(args: &ArgMatches, output: OutputMode) -> Result<i32> {
+    let history_path =
+        get_history_path().ok_or_else(|| anyhow!("Could not detect correct history path"))?;
+    let history = load_history(&history_path)?;
+
+    if let Some(id) = args.value_of("show") {
+        let entry = find_entry(&history, id)?;
+        match output {
+            OutputMode::Json => println!("{}", serde_json::to_string_pretty(entry)?),
+            OutputMode::Text => print_entry(entry),
+        }
+        Ok(0)
+    } else if let Some(id) = args.value_of("rerun") {
+        if output == OutputMode::Json {
+            return Err(anyhow!("--rerun can't be combined with --json"));
+        }
+        execute::rerun(find_entry(&history, id)?)
+    } else {
+        let limit = args
+            .value_of("limit")
+            .unwrap_or(DEFAULT_LIST_LIMIT)
+            .parse::<usize>()?;
+        match output {
+            OutputMode::Json => println!(
+                "{}",
+                serde_json::to_string_pretty(last_entries(&history, limit))?
+            ),
+            OutputMode::Text => list_entries(&history, limit),
+        }
+        Ok(0)
+    }
+}
+
+fn find_entry<'a>(history: &'a HistoryFile, id: &str) -> Result<&'a HistoryEntry> {
+    let parsed_id = id.parse::<u64>()?;
+    history
+        .get(parsed_id)
+        .ok_or_else(|| anyhow!("No execution with id '{}' found in history", parsed_id))
+}
+
+/// The `limit` most recent entries, oldest first
+fn last_entries(history: &HistoryFile, limit: usize) -> &[HistoryEntry] {
+    let num_to_skip = history.entries.len().saturating_sub(limit);
+    &history.entries[num_to_skip..]
+}
+
+fn list_entries(history: &HistoryFile, limit: usize) {
+    let now = now_as_secs();
+
+    for entry in last_entries(history, limit) {
+        let num_failed = entry
+            .results
+            .iter()
+            .filter(|r| !r.skipped && r.exit_code != Some(0))
+            .count();
+        let num_skipped = entry.results.iter().filter(|r| r.skipped).count();
+        let skipped_text = if num_skipped == 0 {
+            String::default()
+        } else {
+            format!(", {} skipped", num_skipped)
+        };
+
+        let summary = if num_failed == 0 {
+            format!("({} paths{})", entry.results.len(), skipped_text).bright_black()
+        } else {
+            format!(
+                "({} paths, {} failed{})",
+                entry.results.len(),
+                num_failed,
+                skipped_text
+            )
+            .red()
+        };
+
+        println!(
+            "{:>5}  {:>8}  {} {} {}",
+            entry.id.to_string().yellow(),
+            format_age(now.saturating_sub(entry.timestamp)).bright_black(),
+            entry.selectors.join(" ").bright_black(),
+            entry.command.join(" "),
+            summary
+        );
+    }
+}
+
+fn print_entry(entry: &HistoryEntry) {
+    println!(
+        "{} {}",
+        format!("#{}", entry.id).yellow(),
+        entry.command.join(" ")
+    );
+    println!(
+        "{}",
+        format!(
+            "{}, selectors: {}",
+            format_age(now_as_secs().saturating_sub(entry.timestamp)),
+            entry.selectors.join(" ")
+        )
+        .bright_black()
+    );
+
+    for result in &entry.results {
+        let exit_code = match result.exit_code {
+            _ if result.skipped => "SKIPPED".bright_black(),
+            Some(0) => "0".green(),
+            Some(code) => code.to_string().red(),
+            None => "FAILED TO EXECUTE".red(),
+        };
+
+        println!(
+            "  {}  {}  {}",
+            util::format_path(&result.path),
+            exit_code,
+            format_duration(result.duration_ms).bright_black()
+        );
+    }
+}
+
+fn now_as_secs() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or_default()
+}
+
+fn format_age(seconds: u64) -> String {
+    match seconds {
+        s if s < 60 => format!("{}s ago", s),
+        s if s < 60 * 60 => format!("{}m ago", s / 60),
+        s if s < 60 * 60 * 24 => format!("{}h ago", s / (60 * 60)),
+        s => format!("{}d ago", s / (60 * 60 * 24)),
+    }
+}
+
+fn format_duration(duration_ms: u64) -> String {
+    if duration_ms < 1000 {
+        format!("{}ms", duration_ms)
+    } else {
+        format!("{}.{:02}s", duration_ms / 1000, (duration_ms % 1000) / 10)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_format_age() {
+        assert_eq!(format_age(5), String::from("5s ago"));
+        assert_eq!(format_age(125), String::from("2m ago"));
+        assert_eq!(format_age(60 * 60 * 3 + 5), String::from("3h ago"));
+        assert_eq!(format_age(60 * 60 * 24 * 2), String::from("2d ago"));
+    }
+
+    #[test]
+    fn test_format_duration() {
+        assert_eq!(format_duration(999), String::from("999ms"));
+        assert_eq!(format_duration(1234), String::from("1.23s"));
+    }
+}
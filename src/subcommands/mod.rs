@@ -1,4 +1,6 @@
 pub mod config;
+pub mod doctor;
+pub mod history;
 pub mod status;
 pub mod subcommand;
 pub mod tmux;
@@ -1,159 +1,544 @@
-use super::super::argparse::ParsedArgs;
-use super::super::config::models::ConfigFile;
-use super::super::execute;
 use super::super::util;
-use crate::subcommands::subcommand::MrtSubcommand;
-use clap::SubCommand;
+use crate::context::{OutputMode, RunContext};
+use crate::subcommands::subcommand::Subcommand;
+use crate::table;
+use crate::vcs::{self, RepoStatus};
+use anyhow::{anyhow, Context, Result};
+use clap::{App, Arg, ArgMatches, SubCommand};
 use colored::{ColoredString, Colorize};
-use std::path::Path;
-use std::process::Command;
-use std::{cmp::max, collections::VecDeque};
+use notify::{EventKind, RecursiveMode, Watcher};
+use rayon::prelude::*;
+use std::cmp::{max, min};
+use std::path::PathBuf;
+use std::sync::mpsc::{self, Receiver};
+use std::thread;
+use std::time::{Duration, Instant};
+
+const DEFAULT_JOBS: &str = "16";
+const DEFAULT_FETCH_TIMEOUT: &str = "30";
+const DEFAULT_WATCH_INTERVAL: &str = "10";
+const WATCH_DEBOUNCE: Duration = Duration::from_millis(300);
+const CLEAR_SCREEN: &str = "\x1b[2J\x1b[H";
+const CHANGED_MARKER: &str = "*";
+const MIN_PATH_WIDTH: usize = 20;
+const COLUMN_NAMES: &[&str] = &[
+    "path",
+    "changes",
+    "branch",
+    "upstream",
+    "sync",
+    "operation",
+    "stash",
+    "default",
+    "submodules",
+    "worktrees",
+];
+const DEFAULT_COLUMNS: &[&str] = &[
+    "path",
+    "changes",
+    "branch",
+    "sync",
+    "default",
+    "operation",
+    "stash",
+    "submodules",
+    "worktrees",
+];
+
+pub struct Status;
+
+impl Subcommand for Status {
+    fn name(&self) -> &'static str {
+        "status"
+    }
 
-const DEFAULT_BRANCH: &str = "master";
+    fn doc(&self) -> App<'static, 'static> {
+        SubCommand::with_name(self.name())
+        .about("Status of directories with specified tags")
+        .arg(
+            Arg::with_name("jobs")
+                .short("j")
+                .long("jobs")
+                .value_name("NUM")
+                .default_value(DEFAULT_JOBS)
+                .help("Maximum number of directories to collect status for concurrently"),
+        )
+        .arg(
+            Arg::with_name("fetch").short("f").long("fetch").help(
+                "Fetch from remotes before collecting status, so ahead/behind is up to date",
+            ),
+        )
+        .arg(
+            Arg::with_name("fetch-timeout")
+                .long("fetch-timeout")
+                .value_name("SECONDS")
+                .default_value(DEFAULT_FETCH_TIMEOUT)
+                .help("Maximum number of seconds to wait for fetching each directory"),
+        )
+        .arg(
+            Arg::with_name("dirty").long("dirty").help(
+                "Only show directories with staged, unstaged, untracked or conflicted files",
+            ),
+        )
+        .arg(
+            Arg::with_name("ahead")
+                .long("ahead")
+                .help("Only show directories that are ahead of their upstream"),
+        )
+        .arg(
+            Arg::with_name("behind")
+                .long("behind")
+                .help("Only show directories that are behind their upstream"),
+        )
+        .arg(
+            Arg::with_name("not-default-branch")
+                .long("not-default-branch")
+                .help("Only show directories where the default branch isn't checked out"),
+        )
+        .arg(
+            Arg::with_name("detached")
+                .long("detached")
+                .help("Only show directories with a detached HEAD"),
+        )
+        .arg(
+            Arg::with_name("columns")
+                .long("columns")
+                .value_name("COLUMNS")
+                .takes_value(true)
+                .multiple(true)
+                .require_delimiter(true)
+                .possible_values(COLUMN_NAMES)
+                .help("Comma separated list of columns to show, in the specified order\n[default: path,changes,branch,sync,default,operation,stash,submodules,worktrees]"),
+        )
+        .arg(
+            Arg::with_name("watch")
+                .short("w")
+                .long("watch")
+                .help("Keep refreshing status when files change, rows that changed since last refresh are marked with `*`"),
+        )
+        .arg(
+            Arg::with_name("interval")
+                .long("interval")
+                .value_name("SECONDS")
+                .default_value(DEFAULT_WATCH_INTERVAL)
+                .help("Maximum number of seconds between refreshes in watch mode"),
+        )
+        .after_help(
+            "Filters can be combined, only directories matching all of them are shown.",
+        )
+    }
 
-pub fn get() -> MrtSubcommand {
-    MrtSubcommand {
-        name: String::from("status"),
-        run_subcommand: |_, parsed_args, config| status(parsed_args, config),
-        doc: SubCommand::with_name("status").about("Status of directories with specified tags"),
+    fn run(&self, args: &ArgMatches, context: &RunContext) -> Result<i32> {
+        status(args, context)
+    }
+
+    fn supports_json(&self) -> bool {
+        true
     }
 }
 
-fn status(parsed_arguments: &ParsedArgs, config: ConfigFile) {
-    let paths = execute::get_all_paths(&parsed_arguments.tags, &config, false);
+impl RepoStatus {
+    fn has_vcs(&self) -> bool {
+        self.vcs.as_deref() != Some(vcs::plain::NAME)
+    }
+
+    const fn is_dirty(&self) -> bool {
+        self.staged + self.unstaged + self.untracked + self.conflicted != 0
+    }
 
-    for path in paths {
-        println!("{}", run_status(&path));
+    fn is_on_default_branch(&self) -> bool {
+        self.branch.is_some() && self.branch == self.default_branch
     }
 }
 
-fn run_status(path: &Path) -> String {
-    let default_branch = get_default_branch(path);
-    match run_status_command(path).output() {
-        Ok(output) => format_output(path, &output.stdout, default_branch),
-        _ => format_error(path),
+struct StatusFilter {
+    dirty: bool,
+    ahead: bool,
+    behind: bool,
+    not_default_branch: bool,
+    detached: bool,
+}
+
+impl StatusFilter {
+    fn from_args(args: &ArgMatches) -> Self {
+        Self {
+            dirty: args.is_present("dirty"),
+            ahead: args.is_present("ahead"),
+            behind: args.is_present("behind"),
+            not_default_branch: args.is_present("not-default-branch"),
+            detached: args.is_present("detached"),
+        }
+    }
+
+    fn matches(&self, status: &RepoStatus) -> bool {
+        let checks = [
+            (self.dirty, status.is_dirty()),
+            (self.ahead, status.ahead != 0),
+            (self.behind, status.behind != 0),
+            (
+                self.not_default_branch,
+                status.has_vcs() && !status.is_on_default_branch(),
+            ),
+            (self.detached, status.detached),
+        ];
+
+        checks.iter().all(|(enabled, matching)| {
+            !enabled || (*matching && status.error.is_none() && !status.missing)
+        })
     }
 }
 
-fn get_remote(path: &Path) -> String {
-    let mut cmd = Command::new("git");
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+enum Column {
+    Path,
+    Changes,
+    Branch,
+    Upstream,
+    Sync,
+    Operation,
+    Stash,
+    Default,
+    Submodules,
+    Worktrees,
+}
+
+impl Column {
+    fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "path" => Some(Self::Path),
+            "changes" => Some(Self::Changes),
+            "branch" => Some(Self::Branch),
+            "upstream" => Some(Self::Upstream),
+            "sync" => Some(Self::Sync),
+            "operation" => Some(Self::Operation),
+            "stash" => Some(Self::Stash),
+            "default" => Some(Self::Default),
+            "submodules" => Some(Self::Submodules),
+            "worktrees" => Some(Self::Worktrees),
+            _ => None,
+        }
+    }
+}
 
-    cmd.args(&["remote"]).current_dir(path);
+fn get_columns(args: &ArgMatches) -> Vec<Column> {
+    args.values_of("columns").map_or_else(
+        || {
+            DEFAULT_COLUMNS
+                .iter()
+                .filter_map(|c| Column::from_name(c))
+                .collect()
+        },
+        |names| names.filter_map(Column::from_name).collect(),
+    )
+}
 
-    let maybe_remote = match cmd.output() {
-        Ok(output) => {
-            let output_string = String::from_utf8_lossy(&output.stdout).to_string();
-            let mut lines: VecDeque<String> = output_string.split('\n').map(String::from).collect();
-            lines.pop_front()
+fn status(args: &ArgMatches, context: &RunContext) -> Result<i32> {
+    let selected = context.select_paths()?;
+    // Missing paths are included, so they are listed as missing rather than disappearing from the table
+    let mut paths = [selected.paths, selected.missing].concat();
+    paths.sort();
+
+    let filter = StatusFilter::from_args(args);
+
+    if args.is_present("watch") {
+        if context.output == OutputMode::Json {
+            return Err(anyhow!("--watch can't be combined with --json"));
         }
-        _ => None,
-    };
+        watch(args, &paths, &filter)
+    } else {
+        print_status(args, &paths, &filter, context.output)
+    }
+    .context("Could not collect status...")?;
+    Ok(0)
+}
 
-    maybe_remote.unwrap_or_else(|| String::from("origin"))
+fn print_status(
+    args: &ArgMatches,
+    paths: &[PathBuf],
+    filter: &StatusFilter,
+    output: OutputMode,
+) -> Result<()> {
+    let mut statuses = collect_statuses(args, paths, args.is_present("fetch"))?;
+    statuses.retain(|status| filter.matches(status));
+
+    if output == OutputMode::Json {
+        println!("{}", serde_json::to_string_pretty(&statuses)?);
+    } else {
+        let columns = get_columns(args);
+        for line in format_table(&statuses, &columns, None) {
+            println!("{}", line);
+        }
+    }
+    Ok(())
 }
 
-fn get_default_branch(path: &Path) -> String {
-    let mut cmd = Command::new("git");
-
-    let remote = get_remote(path);
-    cmd.args(&["-c", "color.ui=always"])
-        .args(&[
-            "symbolic-ref",
-            format!("refs/remotes/{}/HEAD", remote).as_str(),
-        ])
-        .current_dir(path);
-
-    let maybe_default = match cmd.output() {
-        Ok(output) => {
-            let output_string = String::from_utf8_lossy(&output.stdout).to_string();
-            let mut lines: VecDeque<String> = output_string.split('\n').map(String::from).collect();
-            lines.pop_front().and_then(|l| {
-                l.split('/')
-                    .map(String::from)
-                    .collect::<Vec<String>>()
-                    .pop()
-            })
+/// Prints status again whenever files in `paths` change or `interval` passes, until interrupted
+fn watch(args: &ArgMatches, paths: &[PathBuf], filter: &StatusFilter) -> Result<()> {
+    let interval_secs = args
+        .value_of("interval")
+        .unwrap_or(DEFAULT_WATCH_INTERVAL)
+        .parse::<u64>()?;
+    let interval = Duration::from_secs(interval_secs);
+    let columns = get_columns(args);
+
+    let (sender, receiver) = mpsc::channel();
+    let mut watcher = notify::recommended_watcher(sender)?;
+    for path in paths.iter().filter(|path| path.exists()) {
+        if let Err(e) = watcher.watch(path, RecursiveMode::Recursive) {
+            eprintln!("{} {}: {}", "Could not watch".yellow(), path.display(), e);
         }
-        _ => None,
-    };
+    }
 
-    match maybe_default {
-        Some(branch) if branch.is_empty() => String::from(DEFAULT_BRANCH),
-        None => String::from(DEFAULT_BRANCH),
-        Some(default_branch) => default_branch,
+    let mut previous: Option<Vec<RepoStatus>> = None;
+    loop {
+        // Fetching writes to the repositories, so it's only done once to avoid refreshing forever
+        let should_fetch = previous.is_none() && args.is_present("fetch");
+        let mut statuses = collect_statuses(args, paths, should_fetch)?;
+        statuses.retain(|status| filter.matches(status));
+
+        let changed: Vec<bool> = statuses
+            .iter()
+            .map(|status| previous.as_ref().is_some_and(|prev| !prev.contains(status)))
+            .collect();
+
+        print!("{}", CLEAR_SCREEN);
+        let headline = format!(
+            "Watching {} directories, refreshing on changes or every {}s...",
+            paths.len(),
+            interval_secs
+        );
+        println!("{}\n", headline.bright_black());
+        for line in format_table(&statuses, &columns, Some(&changed)) {
+            println!("{}", line);
+        }
+
+        previous = Some(statuses);
+        wait_for_changes(&receiver, interval);
     }
 }
 
-pub fn run_status_command(path: &Path) -> Command {
-    let mut cmd = Command::new("git");
+fn wait_for_changes(receiver: &Receiver<notify::Result<notify::Event>>, interval: Duration) {
+    let deadline = Instant::now() + interval;
 
-    cmd.args(&["-c", "color.ui=always"])
-        .args(&["status", "--branch", "--porcelain"])
-        .current_dir(path);
+    loop {
+        match receiver.recv_timeout(deadline.saturating_duration_since(Instant::now())) {
+            Ok(Ok(event)) if !matches!(event.kind, EventKind::Access(_)) => break,
+            Ok(_) => continue,
+            Err(_) => return,
+        }
+    }
 
-    cmd
+    // Changes tend to come in bursts, like when checking out a branch, so we let them settle
+    thread::sleep(WATCH_DEBOUNCE);
+    while receiver.try_recv().is_ok() {}
 }
 
-fn format_error(path: &Path) -> String {
-    let formatted_path = util::format_path(path).red();
-    let path_spaces = get_spaces_with_maxlen(50, formatted_path.len());
+/// Collects status for all paths concurrently, the output keeps the order of `paths`
+fn collect_statuses(
+    args: &ArgMatches,
+    paths: &[PathBuf],
+    should_fetch: bool,
+) -> Result<Vec<RepoStatus>> {
+    let jobs = args
+        .value_of("jobs")
+        .unwrap_or(DEFAULT_JOBS)
+        .parse::<usize>()?;
+    let pool = rayon::ThreadPoolBuilder::new()
+        .num_threads(max(1, min(jobs, paths.len())))
+        .build()?;
+
+    if should_fetch {
+        let timeout_secs = args
+            .value_of("fetch-timeout")
+            .unwrap_or(DEFAULT_FETCH_TIMEOUT)
+            .parse::<u64>()?;
+        let timeout = Duration::from_secs(timeout_secs);
+
+        pool.install(|| {
+            paths.par_iter().for_each(|p| {
+                if let Err(e) = vcs::detect(p).fetch(p, timeout) {
+                    eprintln!("{} {}: {}", "Could not fetch".yellow(), p.display(), e);
+                }
+            })
+        });
+    }
 
-    format!(
-        "{}{}{}",
-        formatted_path,
-        path_spaces,
-        "SOMETHING WRONG".red()
-    )
+    Ok(pool.install(|| paths.par_iter().map(|p| vcs::get_status(p)).collect()))
 }
 
-fn format_output(path: &Path, out: &[u8], default_branch: String) -> String {
-    let output_string = String::from_utf8_lossy(out).to_string();
-    let lines: Vec<String> = output_string.split('\n').map(String::from).collect();
+/// Formats statuses as a table of the specified columns, fitted to the width of the terminal
+/// If `changed` is specified, rows are prefixed with a marker when the status has changed
+fn format_table(
+    statuses: &[RepoStatus],
+    columns: &[Column],
+    changed: Option<&[bool]>,
+) -> Vec<String> {
+    let marker_width = changed.map_or(0, |_| CHANGED_MARKER.len() + table::COLUMN_GAP.len());
+    let path_width = get_path_width(statuses, columns, marker_width);
+
+    let rows: Vec<Vec<String>> = statuses
+        .iter()
+        .enumerate()
+        .map(|(idx, status)| {
+            let marker = changed.map(|c| {
+                if c.get(idx) == Some(&true) {
+                    format!("{}", CHANGED_MARKER.yellow().bold())
+                } else {
+                    " ".repeat(CHANGED_MARKER.len())
+                }
+            });
+
+            marker
+                .into_iter()
+                .chain(
+                    columns
+                        .iter()
+                        .map(|column| format_cell(status, *column, path_width)),
+                )
+                .collect()
+        })
+        .collect();
 
-    let branch = get_colored_branch(&lines, default_branch);
-    let dirtyness = get_dirtyness(&lines);
-    let behindness: String = get_colored_behindness(&lines);
+    table::render(&rows)
+}
 
-    let dirtyness_spaces = get_spaces_with_maxlen(25, dirtyness.len());
+/// Paths get the width that is left in the terminal after the other columns are printed
+fn get_path_width(
+    statuses: &[RepoStatus],
+    columns: &[Column],
+    reserved_width: usize,
+) -> Option<usize> {
+    let terminal_width = table::terminal_width()?;
 
-    let formatted_path = util::format_path(path);
-    let path_spaces = get_spaces_with_maxlen(50, formatted_path.len());
+    let other_rows: Vec<Vec<String>> = statuses
+        .iter()
+        .map(|status| {
+            columns
+                .iter()
+                .filter(|column| **column != Column::Path)
+                .map(|column| format_cell(status, *column, None))
+                .collect()
+        })
+        .collect();
 
-    format!(
-        "{}{}{}{}{}{}",
-        formatted_path, path_spaces, dirtyness, dirtyness_spaces, branch, behindness
-    )
+    let other_widths: usize = table::column_widths(&other_rows)
+        .iter()
+        .filter(|width| **width != 0)
+        .map(|width| width + table::COLUMN_GAP.len())
+        .sum::<usize>()
+        + reserved_width;
+
+    Some(max(
+        MIN_PATH_WIDTH,
+        terminal_width.saturating_sub(other_widths),
+    ))
 }
 
-fn get_spaces_with_maxlen(max_len: i32, string_length: usize) -> String {
-    let x = max_len - (string_length as i32);
-    let y: usize = max(1, x) as usize;
-    " ".repeat(y)
+fn format_cell(status: &RepoStatus, column: Column, path_width: Option<usize>) -> String {
+    let has_error = status.error.is_some();
+
+    match column {
+        Column::Path => {
+            let formatted_path = path_width.map_or_else(
+                || util::format_path(&status.path),
+                |width| util::format_path_truncated(&status.path, width),
+            );
+            if has_error || status.missing {
+                format!("{}", formatted_path.red())
+            } else {
+                formatted_path
+            }
+        }
+        Column::Changes if status.missing => format!("{}", "MISSING".red().bold()),
+        Column::Changes if has_error => format!("{}", "SOMETHING WRONG".red()),
+        Column::Changes if !status.has_vcs() => format!("{}", "No VCS".bright_black()),
+        Column::Changes => get_dirtyness(status),
+        _ if has_error || status.missing || !status.has_vcs() => String::default(),
+        Column::Branch => format!("{}", get_colored_branch(status)),
+        Column::Upstream => status
+            .upstream
+            .as_ref()
+            .map(|upstream| format!("{}", upstream.bright_black()))
+            .unwrap_or_default(),
+        Column::Sync => get_behindness(status.ahead, status.behind)
+            .map(|behindness| format!("{}", behindness.yellow()))
+            .unwrap_or_default(),
+        Column::Operation => status
+            .operation
+            .as_ref()
+            .map(|op| format!("{}", format!("[{} in progress]", op).red().bold()))
+            .unwrap_or_default(),
+        Column::Stash if status.stashes != 0 => {
+            format!("{}", format!("[{} stashed]", status.stashes).bright_black())
+        }
+        Column::Stash => String::default(),
+        Column::Submodules if !status.drifted_submodules.is_empty() => {
+            let text = format!("[{} submodules drifted]", status.drifted_submodules.len());
+            format!("{}", text.yellow())
+        }
+        Column::Submodules => String::default(),
+        Column::Worktrees if !status.worktrees.is_empty() => {
+            let text = format!("[{} worktrees]", status.worktrees.len());
+            format!("{}", text.bright_black())
+        }
+        Column::Worktrees => String::default(),
+        Column::Default => get_default_comparison_text(status)
+            .map(|text| format!("{}", text.bright_black()))
+            .unwrap_or_default(),
+    }
 }
 
-pub fn get_num_dirty_files(lines: &[String]) -> usize {
-    let modified_files = lines
-        .iter()
-        .filter(|l| !(l.starts_with("## ") || l.is_empty()));
+fn get_default_comparison_text(status: &RepoStatus) -> Option<String> {
+    let default_ref = status.default_ref.as_ref()?;
 
-    modified_files.count()
+    if status.merged? {
+        Some(format!("[merged into {}]", default_ref))
+    } else {
+        Some(format!(
+            "[{}: ahead {}, behind {}]",
+            default_ref, status.ahead_of_default, status.behind_default
+        ))
+    }
 }
 
-fn get_dirtyness(lines: &[String]) -> String {
-    let num_modified = get_num_dirty_files(lines);
+fn get_dirtyness_text(status: &RepoStatus) -> Option<String> {
+    let counts = [
+        (status.conflicted, "conflicted"),
+        (status.staged, "staged"),
+        (status.unstaged, "unstaged"),
+        (status.untracked, "untracked"),
+    ];
 
-    if num_modified != 0 {
-        let text = format!("{} modified", num_modified);
-        format!("{}", text.red())
+    let parts: Vec<String> = counts
+        .iter()
+        .filter(|(count, _)| *count != 0)
+        .map(|(count, name)| format!("{} {}", count, name))
+        .collect();
+
+    if parts.is_empty() {
+        None
     } else {
-        format!("{}", "Clean".green())
+        Some(parts.join(", "))
     }
 }
 
-fn get_colored_branch(lines: &[String], default_branch: String) -> ColoredString {
-    get_branch(lines)
+fn get_dirtyness(status: &RepoStatus) -> String {
+    get_dirtyness_text(status).map_or_else(
+        || format!("{}", "Clean".green()),
+        |text| format!("{}", text.red()),
+    )
+}
+
+fn get_colored_branch(status: &RepoStatus) -> ColoredString {
+    if status.detached {
+        return "HEAD (no branch)".yellow();
+    }
+
+    status
+        .branch
+        .as_ref()
         .map(|s| {
-            if s != default_branch {
+            if Some(s) != status.default_branch.as_ref() {
                 s.bright_black()
             } else {
                 s.normal()
@@ -162,97 +547,132 @@ fn get_colored_branch(lines: &[String], default_branch: String) -> ColoredString
         .unwrap_or_else(|| "<UNKNOWN>".yellow())
 }
 
-fn get_branch(lines: &[String]) -> Option<String> {
-    lines.first().map(|branch_line| {
-        let mut split: Vec<String> = branch_line.split("## ").map(String::from).collect();
-        if split.len() > 1 {
-            split.reverse();
-            split.pop();
-            split.reverse();
-        }
-        let joined: String = split.join("## ");
-
-        let mut dotsplit: Vec<String> = joined.split("...").map(String::from).collect();
-        let middle_idx = dotsplit.len() / 2;
-
-        // If no remote
-        if middle_idx == 0 {
-            dotsplit.pop().unwrap_or_default()
-        } else {
-            while dotsplit.len() > middle_idx {
-                dotsplit.pop();
-            }
-            dotsplit.join("...")
-        }
-    })
-}
-
-fn get_behindness(lines: &[String]) -> Option<String> {
-    lines.first().and_then(|branch_line| {
-        if branch_line.ends_with(']') {
-            let mut split: Vec<String> = branch_line.split(" [").map(String::from).collect();
-            split.pop().map(|l| format!("[{}", l))
-        } else {
-            None
-        }
-    })
-}
-
-fn get_colored_behindness(lines: &[String]) -> String {
-    get_behindness(lines)
-        .map(|b| format!(" {}", b.yellow()))
-        .unwrap_or_default()
+fn get_behindness(ahead: usize, behind: usize) -> Option<String> {
+    match (ahead, behind) {
+        (0, 0) => None,
+        (a, 0) => Some(format!("[ahead {}]", a)),
+        (0, b) => Some(format!("[behind {}]", b)),
+        (a, b) => Some(format!("[ahead {}, behind {}]", a, b)),
+    }
 }
 
 #[cfg(test)]
 mod test {
     use super::*;
 
-    fn to_string_vec(v: Vec<&str>) -> Vec<String> {
-        v.into_iter().map(|s| s.to_owned()).collect()
+    #[test]
+    fn test_get_behindness_func() {
+        assert_eq!(get_behindness(0, 1), Some(String::from("[behind 1]")));
+        assert_eq!(get_behindness(2, 0), Some(String::from("[ahead 2]")));
+        assert_eq!(
+            get_behindness(2, 3),
+            Some(String::from("[ahead 2, behind 3]"))
+        );
+        assert_eq!(get_behindness(0, 0), None);
     }
 
     #[test]
-    fn test_get_behindness_func() {
-        let input1 = to_string_vec(vec!["## mas...[ter...origin/mas...[ter [behind 1]"]);
-        let input2 = to_string_vec(vec!["## master...origin/master [behind 2]"]);
-        let input3 = to_string_vec(vec!["## mas...[ter...origin/mas...[ter"]);
-        let input4 = to_string_vec(vec!["## master...origin/master"]);
-
-        let expected1 = String::from("[behind 1]");
-        let expected2 = String::from("[behind 2]");
-
-        assert_eq!(get_behindness(&input1), Some(expected1));
-        assert_eq!(get_behindness(&input2), Some(expected2));
-        assert_eq!(get_behindness(&input3), None);
-        assert_eq!(get_behindness(&input4), None);
+    fn test_default_comparison_text() {
+        let compared = |ahead_of_default, merged| RepoStatus {
+            default_ref: Some(String::from("origin/master")),
+            ahead_of_default,
+            behind_default: 1,
+            merged,
+            ..RepoStatus::default()
+        };
+        let merged = compared(0, Some(true));
+        let unmerged = compared(1, Some(false));
+        let on_default = compared(0, None);
+
+        assert_eq!(
+            get_default_comparison_text(&merged),
+            Some(String::from("[merged into origin/master]"))
+        );
+        assert_eq!(
+            get_default_comparison_text(&unmerged),
+            Some(String::from("[origin/master: ahead 1, behind 1]"))
+        );
+        assert_eq!(get_default_comparison_text(&on_default), None);
+        assert_eq!(get_default_comparison_text(&RepoStatus::default()), None);
+    }
+
+    #[test]
+    fn test_status_filters_are_combined() {
+        let no_filter = StatusFilter {
+            dirty: false,
+            ahead: false,
+            behind: false,
+            not_default_branch: false,
+            detached: false,
+        };
+        let dirty_and_behind = StatusFilter {
+            dirty: true,
+            behind: true,
+            ..no_filter
+        };
+        let not_default = StatusFilter {
+            not_default_branch: true,
+            ..no_filter
+        };
+
+        let clean = RepoStatus {
+            branch: Some(String::from("master")),
+            default_branch: Some(String::from("master")),
+            ..RepoStatus::default()
+        };
+        let dirty = RepoStatus {
+            untracked: 2,
+            ..RepoStatus::default()
+        };
+        let dirty_and_behind_status = RepoStatus {
+            staged: 1,
+            behind: 3,
+            ..RepoStatus::default()
+        };
+
+        assert!(no_filter.matches(&clean));
+        assert!(!dirty_and_behind.matches(&clean));
+        assert!(!dirty_and_behind.matches(&dirty));
+        assert!(dirty_and_behind.matches(&dirty_and_behind_status));
+        assert!(!not_default.matches(&clean));
+        assert!(not_default.matches(&dirty));
     }
 
     #[test]
-    fn test_get_branch_func() {
-        let input1 = to_string_vec(vec!["## master...origin/master"]);
-        let input2 = to_string_vec(vec!["## mas## ter...origin/mas## ter"]);
-        let input3 = to_string_vec(vec!["## mas...## ter...origin/mas...## ter"]);
-        let input4 = to_string_vec(vec!["## mas...[ter...origin/mas...[ter [behind 1]"]);
-        let input5 = to_string_vec(vec!["## master...origin/master [behind 1]"]);
-
-        let expected1 = String::from("master");
-        let expected2 = String::from("mas## ter");
-        let expected3 = String::from("mas...## ter");
-        let expected4 = String::from("mas...[ter");
-        let expected5 = String::from("master");
-
-        assert_eq!(get_branch(&input1), Some(expected1));
-        assert_eq!(get_branch(&input2), Some(expected2));
-        assert_eq!(get_branch(&input3), Some(expected3));
-        assert_eq!(get_branch(&input4), Some(expected4));
-        assert_eq!(get_branch(&input5), Some(expected5));
+    fn test_dirtyness_text() {
+        let dirty = RepoStatus {
+            staged: 1,
+            unstaged: 1,
+            untracked: 1,
+            ..RepoStatus::default()
+        };
+        assert_eq!(
+            get_dirtyness_text(&dirty),
+            Some(String::from("1 staged, 1 unstaged, 1 untracked"))
+        );
+        assert_eq!(get_dirtyness_text(&RepoStatus::default()), None);
     }
 
     #[test]
-    fn test_get_branch_without_remote() {
-        let input = to_string_vec(vec!["## some-branch"]);
-        let expected = String::from("some-branch");
-        assert_eq!(get_branch(&input), Some(expected));
+    fn test_status_of_plain_and_missing_directories() -> Result<()> {
+        let dir = tempdir::TempDir::new("mrttest")?;
+
+        let plain = vcs::get_status(dir.path());
+        assert_eq!(plain.vcs, Some(String::from(vcs::plain::NAME)));
+        assert_eq!(plain.error, None);
+        assert!(!plain.has_vcs());
+        assert_eq!(get_dirtyness_text(&plain), None);
+
+        let missing = vcs::get_status(&dir.path().join("missing"));
+        assert!(missing.missing);
+        assert_eq!(missing.error, None);
+        assert_eq!(missing.vcs, None);
+        assert_eq!(
+            format_cell(&missing, Column::Changes, None),
+            format!("{}", "MISSING".red().bold())
+        );
+
+        dir.close()?;
+        Ok(())
     }
 }
@@ -1,14 +1,110 @@
-use crate::argparse::ParsedArgs;
-use crate::config::models::ConfigFile;
-use crate::subcommands::{config, status, tmux};
+use crate::context::{OutputMode, RunContext};
+use crate::subcommands::{config, doctor, history, status, tmux};
+use anyhow::{anyhow, Result};
 use clap::{App, ArgMatches};
 
-pub struct MrtSubcommand {
-    pub name: String,
-    pub run_subcommand: fn(&ArgMatches, &ParsedArgs, ConfigFile) -> (),
-    pub doc: App<'static, 'static>,
+/// A subcommand of mrt, like `mrt status`
+pub trait Subcommand {
+    fn name(&self) -> &'static str;
+
+    /// Arguments and help text of the subcommand
+    fn doc(&self) -> App<'static, 'static>;
+
+    /// Runs the subcommand and returns the exit code of mrt, errors are printed by `main`.
+    /// `args` are the arguments of the subcommand, the arguments to mrt itself are in `context`
+    fn run(&self, args: &ArgMatches, context: &RunContext) -> Result<i32>;
+
+    /// Whether the subcommand prints json with `--json`, mrt refuses the flag otherwise
+    fn supports_json(&self) -> bool {
+        false
+    }
+}
+
+pub fn get_subcommands() -> Vec<Box<dyn Subcommand>> {
+    vec![
+        Box::new(status::Status),
+        Box::new(config::Config),
+        Box::new(tmux::Tmux),
+        Box::new(history::History),
+        Box::new(doctor::Doctor),
+    ]
+}
+
+/// Runs the subcommand named `name` with the arguments clap matched for it
+pub fn run_subcommand(
+    subcommands: &[Box<dyn Subcommand>],
+    name: &str,
+    args: &ArgMatches,
+    context: &RunContext,
+) -> Result<i32> {
+    let found = subcommands
+        .iter()
+        .find(|cmd| cmd.name() == name)
+        .ok_or_else(|| anyhow!("Unknown subcommand '{}'", name))?;
+    check_output_supported(found.as_ref(), context)?;
+    found.run(args, context)
 }
 
-pub fn get_subcommands() -> Vec<MrtSubcommand> {
-    vec![status::get(), config::get(), tmux::get()]
+fn check_output_supported(subcommand: &dyn Subcommand, context: &RunContext) -> Result<()> {
+    if context.output == OutputMode::Json && !subcommand.supports_json() {
+        return Err(anyhow!("mrt {} doesn't support --json", subcommand.name()));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::argparse::args::JSON_OUTPUT_ARG;
+    use crate::argparse::ParsedArgs;
+    use crate::config::models::ConfigFile;
+    use clap::Arg;
+
+    /// Subcommands that print json when `context.output` is `OutputMode::Json`
+    const JSON_SUBCOMMANDS: &[&str] = &["status", "config", "history", "doctor"];
+
+    fn json_context() -> RunContext {
+        let matches = App::new("mrt")
+            .arg(Arg::with_name(JSON_OUTPUT_ARG).long(JSON_OUTPUT_ARG))
+            .get_matches_from(["mrt", "--json"]);
+        let parsed_args = ParsedArgs {
+            tags: vec![],
+            before_tags: vec![],
+            after_tags: vec![],
+        };
+        RunContext::from_args(&matches, &parsed_args, ConfigFile::new())
+    }
+
+    #[test]
+    fn test_json_is_only_accepted_by_subcommands_printing_it() -> Result<()> {
+        let subcommands = get_subcommands();
+        let context = json_context();
+
+        for subcommand in &subcommands {
+            let accepted = check_output_supported(subcommand.as_ref(), &context).is_ok();
+            assert_eq!(
+                accepted,
+                JSON_SUBCOMMANDS.contains(&subcommand.name()),
+                "{}",
+                subcommand.name()
+            );
+        }
+
+        // History and doctor read the files of the user, so only these are run
+        for args in [&["status"][..], &["config", "--list"]] {
+            let subcommand = subcommands
+                .iter()
+                .find(|cmd| cmd.name() == args[0])
+                .ok_or_else(|| anyhow!("Missing subcommand {}", args[0]))?;
+            let matches = subcommand.doc().get_matches_from(args);
+            assert_eq!(
+                run_subcommand(&subcommands, args[0], &matches, &context)?,
+                0
+            );
+        }
+
+        let result = run_subcommand(&subcommands, "tmux", &ArgMatches::default(), &context);
+        assert!(matches!(result, Err(e) if e.to_string().contains("doesn't support --json")));
+        Ok(())
+    }
 }
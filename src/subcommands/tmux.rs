@@ -3,39 +3,39 @@ use std::{
     process::Command,
 };
 
-use super::subcommand::MrtSubcommand;
-use crate::{argparse::ParsedArgs, config::models::ConfigFile, execute, APP_SHORT_NAME};
-use anyhow::Result;
-use clap::{Arg, ArgMatches, SubCommand};
+use super::subcommand::Subcommand;
+use crate::{context::RunContext, APP_SHORT_NAME};
+use anyhow::{Context, Result};
+use clap::{App, Arg, ArgMatches, SubCommand};
 use uuid::Uuid;
 
-pub fn get() -> MrtSubcommand {
-    MrtSubcommand {
-        name: String::from("tmux"),
-        run_subcommand: tmux,
-        doc: SubCommand::with_name("tmux")
+pub struct Tmux;
+
+impl Subcommand for Tmux {
+    fn name(&self) -> &'static str {
+        "tmux"
+    }
+
+    fn doc(&self) -> App<'static, 'static> {
+        SubCommand::with_name(self.name())
             .about("Launch a tmux session, with panes opened in directories of the specified tags")
             .arg(
                 Arg::with_name("detached")
                     .short("d")
                     .long("detached")
                     .help("Whether or not the tmux session should spawn in a detached state."),
-            ),
+            )
     }
-}
 
-fn tmux(args: &ArgMatches, parsed_arguments: &ParsedArgs, config: ConfigFile) {
-    match open_tmux(args, parsed_arguments, config) {
-        Ok(_) => {}
-        Err(e) => {
-            eprintln!("We got an error when trying to open tmux:\n{}", e)
-        }
-    };
+    fn run(&self, args: &ArgMatches, context: &RunContext) -> Result<i32> {
+        open_tmux(args, context).context("Could not open tmux...")?;
+        Ok(0)
+    }
 }
 
-fn open_tmux(args: &ArgMatches, parsed_arguments: &ParsedArgs, config: ConfigFile) -> Result<()> {
+fn open_tmux(args: &ArgMatches, context: &RunContext) -> Result<()> {
+    let paths = context.select_paths()?.paths;
     let session_name = spawn_new_session()?;
-    let paths = execute::get_all_paths(&parsed_arguments.tags, &config, false);
     open_panes(session_name.as_str(), paths)?;
     if !args.is_present("detached") {
         attach_tmux(session_name.as_str())?;
@@ -45,14 +45,14 @@ fn open_tmux(args: &ArgMatches, parsed_arguments: &ParsedArgs, config: ConfigFil
 
 fn attach_tmux(session_name: &str) -> Result<()> {
     let mut cmd = Command::new("tmux");
-    cmd.args(&["attach", "-t", session_name]);
+    cmd.args(["attach", "-t", session_name]);
     cmd.spawn()?.wait()?;
     Ok(())
 }
 
 fn set_layout(session_name: &str) -> Result<()> {
     let mut cmd = Command::new("tmux");
-    cmd.args(&["select-layout", "-t", session_name, "tiled"]);
+    cmd.args(["select-layout", "-t", session_name, "tiled"]);
     cmd.spawn()?.wait()?;
 
     Ok(())
@@ -70,12 +70,12 @@ fn open_panes(session_name: &str, paths: Vec<PathBuf>) -> Result<()> {
 fn open_pane_at(session_name: &str, path: &Path, skip_split: bool) -> Result<()> {
     let mut cd_cmd = Command::new("tmux");
     let cd = format!("cd {}", path.to_string_lossy());
-    cd_cmd.args(&["send-keys", "-t", session_name, cd.as_str(), "Enter"]);
+    cd_cmd.args(["send-keys", "-t", session_name, cd.as_str(), "Enter"]);
     cd_cmd.spawn()?.wait()?;
 
     if !skip_split {
         let mut split_cmd = Command::new("tmux");
-        split_cmd.args(&["split-window", "-v", "-t", session_name]);
+        split_cmd.args(["split-window", "-v", "-t", session_name]);
         split_cmd.spawn()?.wait()?;
     }
 
@@ -94,7 +94,7 @@ fn spawn_new_session() -> Result<String> {
     println!("Spawning tmux session: '{}'", session_name);
 
     let mut cmd = Command::new("tmux");
-    cmd.args(&["new-session", "-d", "-s", session_name.as_str()]);
+    cmd.args(["new-session", "-d", "-s", session_name.as_str()]);
     cmd.spawn()?.wait()?; // Consider failing if exit-code != 0
 
     Ok(session_name)
@@ -0,0 +1,145 @@
+use std::iter::once;
+use terminal_size::{terminal_size, Width};
+use unicode_width::{UnicodeWidthChar, UnicodeWidthStr};
+
+pub const COLUMN_GAP: &str = "  ";
+const ELLIPSIS: char = '…';
+
+pub fn terminal_width() -> Option<usize> {
+    terminal_size().map(|(Width(width), _)| width as usize)
+}
+
+/// Width of the string when printed in a terminal, ANSI escape codes are not counted
+pub fn visible_width(s: &str) -> usize {
+    strip_ansi(s).width()
+}
+
+fn strip_ansi(s: &str) -> String {
+    let mut stripped = String::with_capacity(s.len());
+    let mut chars = s.chars();
+
+    while let Some(c) = chars.next() {
+        if c == '\x1b' {
+            // Control sequences look like `ESC[...m`, where the final char is in the range '@'..='~'
+            if chars.next() == Some('[') {
+                for c in chars.by_ref() {
+                    if ('@'..='~').contains(&c) {
+                        break;
+                    }
+                }
+            }
+        } else {
+            stripped.push(c);
+        }
+    }
+
+    stripped
+}
+
+/// Truncates from the start of the string, prefixing it with `…` if anything was removed
+pub fn truncate_start(s: &str, max_width: usize) -> String {
+    if s.width() <= max_width {
+        return s.to_string();
+    }
+
+    let kept: Vec<char> = take_within_width(s.chars().rev(), max_width.saturating_sub(1));
+    once(ELLIPSIS).chain(kept.into_iter().rev()).collect()
+}
+
+/// Truncates from the end of the string, suffixing it with `…` if anything was removed
+pub fn truncate_end(s: &str, max_width: usize) -> String {
+    if s.width() <= max_width {
+        return s.to_string();
+    }
+
+    let kept: Vec<char> = take_within_width(s.chars(), max_width.saturating_sub(1));
+    kept.into_iter().chain(once(ELLIPSIS)).collect()
+}
+
+fn take_within_width(chars: impl Iterator<Item = char>, max_width: usize) -> Vec<char> {
+    let mut width = 0;
+    chars
+        .take_while(|c| {
+            width += c.width().unwrap_or(0);
+            width <= max_width
+        })
+        .collect()
+}
+
+/// Widest visible cell for every column in `rows`
+pub fn column_widths(rows: &[Vec<String>]) -> Vec<usize> {
+    let num_columns = rows.iter().map(Vec::len).max().unwrap_or(0);
+
+    (0..num_columns)
+        .map(|idx| {
+            rows.iter()
+                .filter_map(|row| row.get(idx))
+                .map(|cell| visible_width(cell))
+                .max()
+                .unwrap_or(0)
+        })
+        .collect()
+}
+
+/// Pads cells so columns line up, columns where every cell is empty are left out
+pub fn render(rows: &[Vec<String>]) -> Vec<String> {
+    let widths = column_widths(rows);
+
+    rows.iter()
+        .map(|row| {
+            let cells: Vec<String> = row
+                .iter()
+                .zip(&widths)
+                .filter(|(_, width)| **width != 0)
+                .map(|(cell, width)| {
+                    let padding = width.saturating_sub(visible_width(cell));
+                    format!("{}{}", cell, " ".repeat(padding))
+                })
+                .collect();
+
+            cells.join(COLUMN_GAP).trim_end().to_string()
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use colored::Colorize;
+
+    #[test]
+    fn test_visible_width_ignores_colors() {
+        colored::control::set_override(true);
+        let colored_text = format!("{} {}", "master".yellow(), "å".red().bold());
+        colored::control::unset_override();
+
+        assert_ne!(colored_text.len(), 8);
+        assert_eq!(visible_width(&colored_text), 8);
+        assert_eq!(visible_width("日本"), 4);
+    }
+
+    #[test]
+    fn test_truncation() {
+        assert_eq!(
+            truncate_start("~/dev/some/dir/", 10),
+            String::from("…some/dir/")
+        );
+        assert_eq!(truncate_end("some-long-name", 6), String::from("some-…"));
+        assert_eq!(truncate_end("short", 6), String::from("short"));
+    }
+
+    #[test]
+    fn test_render_aligns_columns() {
+        let rows = vec![
+            vec![String::from("a"), String::new(), String::from("x")],
+            vec![String::from("abc"), String::new(), String::from("y")],
+        ];
+
+        let rendered = render(&rows);
+
+        assert_eq!(
+            rendered,
+            vec![String::from("a    x"), String::from("abc  y")]
+        );
+    }
+}
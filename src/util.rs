@@ -1,25 +1,77 @@
+use crate::table;
+use anyhow::{anyhow, Result};
 use colored::Colorize;
-use std::path::{Path, PathBuf};
+use fs2::FileExt;
+use std::fs::{self, OpenOptions};
+use std::io::Write;
+use std::path::{Component, Path, PathBuf};
+use tempfile::NamedTempFile;
+use unicode_width::UnicodeWidthStr;
 
 pub fn expand_path(input: &str) -> PathBuf {
     let expanded = String::from(shellexpand::tilde(input));
     PathBuf::from(expanded)
 }
 
-pub fn expand_pathbuf(input: PathBuf) -> PathBuf {
-    let path_as_str = input.to_str().unwrap_or_default();
-    let expanded = String::from(shellexpand::tilde(path_as_str));
-    PathBuf::from(expanded)
+/// Expands `~` and environment variables like `$WORKSPACE` or `${WORKSPACE}`, failing if a variable is not set
+pub fn expand_pathbuf(input: PathBuf) -> Result<PathBuf> {
+    let path_as_str = input.to_string_lossy();
+    let expanded = shellexpand::full(&path_as_str).map_err(|e| {
+        anyhow!(
+            "Environment variable '{}' used in '{}' is not set",
+            e.var_name,
+            input.display()
+        )
+    })?;
+    Ok(PathBuf::from(String::from(expanded)))
+}
+
+/// Removes `.` and `..` components without touching the filesystem, so symlinks are kept
+pub fn normalize_path(path: &Path) -> PathBuf {
+    let mut normalized = PathBuf::new();
+    for component in path.components() {
+        match component {
+            Component::CurDir => {}
+            Component::ParentDir if normalized.file_name().is_some() => {
+                normalized.pop();
+            }
+            _ => normalized.push(component),
+        }
+    }
+    normalized
+}
+
+/// Path to `path` from the directory `base`, both should be absolute and normalized
+pub fn relative_path(path: &Path, base: &Path) -> PathBuf {
+    let path_components: Vec<Component> = path.components().collect();
+    let base_components: Vec<Component> = base.components().collect();
+    let num_common = path_components
+        .iter()
+        .zip(&base_components)
+        .take_while(|(a, b)| a == b)
+        .count();
+
+    let mut relative: PathBuf = base_components[num_common..]
+        .iter()
+        .map(|_| Component::ParentDir)
+        .collect();
+    relative.extend(&path_components[num_common..]);
+
+    if relative.as_os_str().is_empty() {
+        PathBuf::from(".")
+    } else {
+        relative
+    }
 }
 
 fn format_path_with_homedir(path: &Path, home_dir: Option<PathBuf>, sep: char) -> (String, String) {
     let base_name = path.file_name().map(|x| x.to_str().unwrap_or(""));
     let dir_name = path.parent().map(|x| x.to_str().unwrap_or(""));
 
-    let dir_to_use = match home_dir {
-        Some(home) => dir_name.map(|dn| dn.replace(home.to_str().unwrap_or(""), "~")),
-        _ => dir_name.map(|x| x.to_string()),
-    };
+    let dir_to_use = home_dir.map_or_else(
+        || dir_name.map(|x| x.to_string()),
+        |home| dir_name.map(|dn| dn.replace(home.to_str().unwrap_or(""), "~")),
+    );
 
     let sep_to_use = match &dir_to_use {
         Some(d) if d.ends_with('/') => String::default(),
@@ -37,11 +89,81 @@ pub fn format_path(path: &Path) -> String {
     format!("{}{}", prefix.dimmed(), basename.normal())
 }
 
+/// Formats path so it fits within `max_width`, the basename is kept intact for as long as possible
+pub fn format_path_truncated(path: &Path, max_width: usize) -> String {
+    let (prefix, basename) = truncate_split_path(split_on_basename(path), max_width);
+    format!("{}{}", prefix.dimmed(), basename.normal())
+}
+
+fn truncate_split_path((prefix, basename): (String, String), max_width: usize) -> (String, String) {
+    let basename_width = basename.width();
+
+    if prefix.width() + basename_width <= max_width {
+        (prefix, basename)
+    } else if basename_width < max_width {
+        (
+            table::truncate_start(&prefix, max_width - basename_width),
+            basename,
+        )
+    } else {
+        (String::default(), table::truncate_end(&basename, max_width))
+    }
+}
+
 pub fn split_on_basename(path: &Path) -> (String, String) {
     let home_dir = dirs::home_dir();
     format_path_with_homedir(path, home_dir, std::path::MAIN_SEPARATOR)
 }
 
+/// Runs `f` while holding an exclusive advisory lock on `path` with `.lock` appended.
+/// The lock is taken on a separate file, since writing atomically replaces the file itself.
+/// The directory of `path` is created if it is missing, so `f` can write the file
+pub fn with_file_lock<T, F>(path: &Path, f: F) -> Result<T>
+where
+    F: FnOnce() -> Result<T>,
+{
+    if let Some(parent) = path.parent().filter(|p| !p.as_os_str().is_empty()) {
+        fs::create_dir_all(parent)?;
+    }
+
+    let lock_file = OpenOptions::new()
+        .create(true)
+        .truncate(false)
+        .write(true)
+        .open(get_lock_path(path))?;
+
+    lock_file.lock_exclusive()?;
+    let result = f();
+    lock_file.unlock()?;
+    result
+}
+
+fn get_lock_path(path: &Path) -> PathBuf {
+    let mut lock_name = path.file_name().unwrap_or_default().to_os_string();
+    lock_name.push(".lock");
+    path.with_file_name(lock_name)
+}
+
+/// Writes to a temporary file that is renamed over `path`, so the file is never left half written.
+/// Symlinks are followed, so a config linked from a dotfiles repository is updated there and stays a link,
+/// and the permissions of the existing file are kept
+pub fn write_file_atomically(path: &Path, data: &[u8]) -> Result<()> {
+    let target = fs::canonicalize(path).unwrap_or_else(|_| path.to_path_buf());
+    let dir = target
+        .parent()
+        .filter(|parent| !parent.as_os_str().is_empty())
+        .unwrap_or_else(|| Path::new("."));
+
+    let mut file = NamedTempFile::new_in(dir)?;
+    file.write_all(data)?;
+    if let Ok(metadata) = fs::metadata(&target) {
+        file.as_file().set_permissions(metadata.permissions())?;
+    }
+    file.as_file().sync_all()?;
+    file.persist(&target)?;
+    Ok(())
+}
+
 #[cfg(test)]
 mod test {
     use super::*;
@@ -60,6 +182,22 @@ mod test {
         assert_eq!(result2, expected2);
     }
 
+    #[test]
+    fn test_path_truncation() {
+        let split = (String::from("~/dev/projects/"), String::from("mrt"));
+
+        let fits = truncate_split_path(split.clone(), 18);
+        let shortened_prefix = truncate_split_path(split.clone(), 10);
+        let shortened_basename = truncate_split_path(split, 2);
+
+        assert_eq!(fits, (String::from("~/dev/projects/"), String::from("mrt")));
+        assert_eq!(
+            shortened_prefix,
+            (String::from("…jects/"), String::from("mrt"))
+        );
+        assert_eq!(shortened_basename, (String::new(), String::from("m…")));
+    }
+
     #[test]
     fn test_formatting_on_root() {
         let path1 = PathBuf::from("/home");
@@ -82,4 +220,73 @@ mod test {
         assert_eq!(result1, expected1);
         assert_eq!(result2, expected2);
     }
+
+    #[test]
+    fn test_normalize_path() {
+        assert_eq!(
+            normalize_path(Path::new("/work/meta/../service-a/./src")),
+            PathBuf::from("/work/service-a/src")
+        );
+        assert_eq!(
+            normalize_path(Path::new("../service-a")),
+            PathBuf::from("../service-a")
+        );
+    }
+
+    #[test]
+    fn test_expand_environment_variables() -> Result<()> {
+        std::env::set_var("MRT_TEST_WORKSPACE", "/work");
+        assert_eq!(
+            expand_pathbuf(PathBuf::from("$MRT_TEST_WORKSPACE/service-a"))?,
+            PathBuf::from("/work/service-a")
+        );
+        assert_eq!(
+            expand_pathbuf(PathBuf::from("${MRT_TEST_WORKSPACE}/service-b"))?,
+            PathBuf::from("/work/service-b")
+        );
+
+        let unset = expand_pathbuf(PathBuf::from("$MRT_TEST_UNSET_VARIABLE/service-a"));
+        assert_eq!(
+            unset.map_err(|e| e.to_string()),
+            Err(String::from("Environment variable 'MRT_TEST_UNSET_VARIABLE' used in '$MRT_TEST_UNSET_VARIABLE/service-a' is not set"))
+        );
+        Ok(())
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_write_atomically_through_symlink() -> Result<()> {
+        use std::os::unix::fs::{symlink, PermissionsExt};
+
+        let dir = tempdir::TempDir::new("mrttest")?;
+        let target = dir.path().join("dotfiles").join("mrtconfig.json");
+        let link = dir.path().join(".mrtconfig.json");
+        fs::create_dir(dir.path().join("dotfiles"))?;
+        fs::write(&target, "old")?;
+        fs::set_permissions(&target, fs::Permissions::from_mode(0o644))?;
+        symlink(&target, &link)?;
+
+        write_file_atomically(&link, b"new")?;
+
+        assert!(fs::symlink_metadata(&link)?.file_type().is_symlink());
+        assert_eq!(fs::read_to_string(&target)?, "new");
+        assert_eq!(fs::metadata(&target)?.permissions().mode() & 0o777, 0o644);
+
+        dir.close()?;
+        Ok(())
+    }
+
+    #[test]
+    fn test_relative_path() {
+        let base = Path::new("/work/meta");
+        assert_eq!(
+            relative_path(Path::new("/work/service-a"), base),
+            PathBuf::from("../service-a")
+        );
+        assert_eq!(
+            relative_path(Path::new("/work/meta/tools"), base),
+            PathBuf::from("tools")
+        );
+        assert_eq!(relative_path(base, base), PathBuf::from("."));
+    }
 }
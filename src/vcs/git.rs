@@ -0,0 +1,522 @@
+use super::{wait_with_timeout, FileCounts, RepoStatus, Vcs};
+use anyhow::Result;
+use git2::{BranchType, Repository, RepositoryState, Status, StatusOptions};
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+use std::time::Duration;
+
+const DEFAULT_BRANCH: &str = "master";
+const DEFAULT_REMOTE: &str = "origin";
+
+pub struct Git;
+
+impl Vcs for Git {
+    fn name(&self) -> &'static str {
+        "git"
+    }
+
+    fn detect(&self, path: &Path) -> bool {
+        path.join(".git").exists()
+    }
+
+    fn status(&self, path: &Path) -> Result<RepoStatus> {
+        get_repo_status(path)
+    }
+
+    fn is_modified(&self, path: &Path) -> Result<bool> {
+        let repo = Repository::open(path)?;
+        Ok(get_num_dirty_files(&repo)? > 0)
+    }
+
+    /// Fetching is done with the git executable so the users credential helpers and ssh config are used
+    fn fetch(&self, path: &Path, timeout: Duration) -> Result<()> {
+        let child = Command::new("git")
+            .args(["fetch", "--quiet"])
+            .current_dir(path)
+            .env("GIT_TERMINAL_PROMPT", "0")
+            .stdin(Stdio::null())
+            .stdout(Stdio::null())
+            .stderr(Stdio::null())
+            .spawn()?;
+
+        wait_with_timeout(child, timeout)
+    }
+}
+
+fn get_repo_status(path: &Path) -> Result<RepoStatus> {
+    let mut repo = Repository::open(path)?;
+    let detached = repo.head_detached().unwrap_or(false);
+    let branch = if detached { None } else { get_branch(&repo) };
+    let (upstream, ahead, behind) = branch
+        .as_ref()
+        .and_then(|b| get_upstream(&repo, b))
+        .map_or((None, 0, 0), |(u, a, b)| (Some(u), a, b));
+    let counts = get_file_counts(&repo)?;
+    let default_branch = get_default_branch(&repo);
+    let (default_ref, ahead_of_default, behind_default) =
+        get_default_comparison(&repo, &default_branch)
+            .map_or((None, 0, 0), |(r, a, b)| (Some(r), a, b));
+    let is_on_default = branch.as_ref() == Some(&default_branch);
+    let merged = default_ref
+        .as_ref()
+        .filter(|_| !is_on_default)
+        .map(|_| ahead_of_default == 0);
+
+    Ok(RepoStatus {
+        path: path.to_path_buf(),
+        default_branch: Some(default_branch),
+        default_ref,
+        ahead_of_default,
+        behind_default,
+        merged,
+        stashes: get_num_stashes(&mut repo)?,
+        staged: counts.staged,
+        unstaged: counts.unstaged,
+        untracked: counts.untracked,
+        conflicted: counts.conflicted,
+        operation: get_operation(&repo),
+        drifted_submodules: get_drifted_submodules(&repo),
+        worktrees: get_worktree_paths(&repo),
+        error: None,
+        branch,
+        upstream,
+        ahead,
+        behind,
+        detached,
+        ..RepoStatus::default()
+    })
+}
+
+fn get_remote(repo: &Repository) -> String {
+    repo.remotes()
+        .ok()
+        .and_then(|remotes| remotes.get(0).map(String::from))
+        .unwrap_or_else(|| String::from(DEFAULT_REMOTE))
+}
+
+fn get_default_branch(repo: &Repository) -> String {
+    let remote = get_remote(repo);
+    let remote_head = format!("refs/remotes/{}/HEAD", remote);
+
+    let maybe_default = repo.find_reference(&remote_head).ok().and_then(|r| {
+        r.symbolic_target()
+            .and_then(|target| target.split('/').next_back())
+            .map(String::from)
+    });
+
+    match maybe_default {
+        Some(branch) if !branch.is_empty() => branch,
+        _ => String::from(DEFAULT_BRANCH),
+    }
+}
+
+/// Returns the reference of the default branch and how far ahead and behind HEAD is compared to it.
+/// The remote tracking branch is preferred, since the local default branch is often outdated
+fn get_default_comparison(
+    repo: &Repository,
+    default_branch: &str,
+) -> Option<(String, usize, usize)> {
+    let head_oid = repo.head().ok()?.target()?;
+    let remote_branch = format!("{}/{}", get_remote(repo), default_branch);
+
+    let (default_name, default_oid) = repo
+        .find_branch(&remote_branch, BranchType::Remote)
+        .or_else(|_| repo.find_branch(default_branch, BranchType::Local))
+        .ok()
+        .and_then(|b| Some((b.name().ok()??.to_string(), b.get().target()?)))?;
+
+    let (ahead, behind) = repo.graph_ahead_behind(head_oid, default_oid).ok()?;
+    Some((default_name, ahead, behind))
+}
+
+fn get_branch(repo: &Repository) -> Option<String> {
+    repo.head().map_or_else(
+        // HEAD points to a branch without commits, so we read the name from the symbolic ref
+        |_| {
+            repo.find_reference("HEAD").ok().and_then(|head| {
+                head.symbolic_target()
+                    .map(|target| target.trim_start_matches("refs/heads/").to_string())
+            })
+        },
+        |head| head.shorthand().map(String::from),
+    )
+}
+
+/// Returns name of the upstream branch and how far ahead and behind `branch` is compared to it
+fn get_upstream(repo: &Repository, branch: &str) -> Option<(String, usize, usize)> {
+    let local = repo.find_branch(branch, BranchType::Local).ok()?;
+    let upstream = local.upstream().ok()?;
+    let upstream_name = upstream.name().ok()??.to_string();
+
+    let local_oid = local.get().target()?;
+    let upstream_oid = upstream.get().target()?;
+
+    let (ahead, behind) = repo.graph_ahead_behind(local_oid, upstream_oid).ok()?;
+    Some((upstream_name, ahead, behind))
+}
+
+fn get_statuses(repo: &Repository) -> Result<git2::Statuses<'_>> {
+    let mut options = StatusOptions::new();
+    options
+        .include_untracked(true)
+        .recurse_untracked_dirs(false)
+        .include_ignored(false);
+
+    Ok(repo.statuses(Some(&mut options))?)
+}
+
+fn get_num_dirty_files(repo: &Repository) -> Result<usize> {
+    Ok(get_statuses(repo)?.len())
+}
+
+fn get_file_counts(repo: &Repository) -> Result<FileCounts> {
+    let staged_flags = Status::INDEX_NEW
+        | Status::INDEX_MODIFIED
+        | Status::INDEX_DELETED
+        | Status::INDEX_RENAMED
+        | Status::INDEX_TYPECHANGE;
+    let unstaged_flags =
+        Status::WT_MODIFIED | Status::WT_DELETED | Status::WT_RENAMED | Status::WT_TYPECHANGE;
+
+    let statuses = get_statuses(repo)?;
+    let count_matching = |flags: Status| {
+        statuses
+            .iter()
+            .filter(|entry| entry.status().intersects(flags))
+            .count()
+    };
+
+    Ok(FileCounts {
+        staged: count_matching(staged_flags),
+        unstaged: count_matching(unstaged_flags),
+        untracked: count_matching(Status::WT_NEW),
+        conflicted: count_matching(Status::CONFLICTED),
+    })
+}
+
+fn get_operation(repo: &Repository) -> Option<String> {
+    let operation = match repo.state() {
+        RepositoryState::Clean => return None,
+        RepositoryState::Merge => "merge",
+        RepositoryState::Revert | RepositoryState::RevertSequence => "revert",
+        RepositoryState::CherryPick | RepositoryState::CherryPickSequence => "cherry-pick",
+        RepositoryState::Bisect => "bisect",
+        RepositoryState::Rebase
+        | RepositoryState::RebaseInteractive
+        | RepositoryState::RebaseMerge => "rebase",
+        RepositoryState::ApplyMailbox | RepositoryState::ApplyMailboxOrRebase => "am",
+    };
+    Some(String::from(operation))
+}
+
+fn get_drifted_submodules(repo: &Repository) -> Vec<String> {
+    repo.submodules()
+        .map(|submodules| {
+            submodules
+                .iter()
+                // Uninitialized submodules have nothing checked out, so they can't have drifted
+                .filter(|submodule| submodule.workdir_id().is_some() && submodule.open().is_ok())
+                .filter(|submodule| submodule.workdir_id() != submodule.head_id())
+                .filter_map(|submodule| submodule.name().map(String::from))
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+/// Paths of submodules that are initialized, and therefore can be used as a working directory
+pub fn get_submodule_paths(repo: &Repository) -> Vec<PathBuf> {
+    let workdir = match repo.workdir() {
+        Some(workdir) => workdir,
+        None => return vec![],
+    };
+
+    repo.submodules()
+        .map(|submodules| {
+            submodules
+                .iter()
+                .filter(|submodule| submodule.open().is_ok())
+                .map(|submodule| workdir.join(submodule.path()))
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+/// Paths of linked worktrees that still exist, the main worktree is not included
+pub fn get_worktree_paths(repo: &Repository) -> Vec<PathBuf> {
+    repo.worktrees()
+        .map(|names| {
+            names
+                .iter()
+                .flatten()
+                .filter_map(|name| repo.find_worktree(name).ok())
+                .filter(|worktree| worktree.validate().is_ok())
+                .map(|worktree| worktree.path().to_path_buf())
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+/// Remotes pointing to local directories that don't exist, as pairs of remote name and url.
+/// Remotes on other hosts are not checked, since that would require network access
+pub fn get_unreachable_local_remotes(repo: &Repository) -> Vec<(String, String)> {
+    let workdir = match repo.workdir() {
+        Some(workdir) => workdir,
+        None => return vec![],
+    };
+
+    repo.remotes()
+        .map(|names| {
+            names
+                .iter()
+                .flatten()
+                .filter_map(|name| {
+                    let url = repo.find_remote(name).ok()?.url()?.to_string();
+                    let local_path = get_local_remote_path(&url, workdir)?;
+                    (!local_path.exists()).then(|| (String::from(name), url))
+                })
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+/// Path of a remote url if it points to a local directory, relative urls are relative to `workdir`
+fn get_local_remote_path(url: &str, workdir: &Path) -> Option<PathBuf> {
+    if let Some(path) = url.strip_prefix("file://") {
+        return Some(PathBuf::from(path));
+    }
+
+    let is_url = url.contains("://");
+    // scp-like syntax, like `git@github.com:user/repo.git`
+    let is_scp_like = url
+        .find(':')
+        .is_some_and(|colon| url.find('/').is_none_or(|slash| colon < slash));
+
+    if is_url || is_scp_like {
+        None
+    } else {
+        Some(workdir.join(url))
+    }
+}
+
+fn get_num_stashes(repo: &mut Repository) -> Result<usize> {
+    let mut num_stashes = 0;
+    repo.stash_foreach(|_, _, _| {
+        num_stashes += 1;
+        true
+    })?;
+    Ok(num_stashes)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::fs::File;
+
+    fn commit_all(repo: &Repository, message: &str) -> Result<git2::Oid> {
+        let mut index = repo.index()?;
+        index.add_all(std::iter::once("*"), git2::IndexAddOption::DEFAULT, None)?;
+        index.write()?;
+        let tree = repo.find_tree(index.write_tree()?)?;
+        let signature = git2::Signature::now("mrt", "mrt@example.com")?;
+        let parent = repo.head().ok().and_then(|h| h.peel_to_commit().ok());
+        let parents: Vec<&git2::Commit> = parent.iter().collect();
+
+        let oid = repo.commit(
+            Some("HEAD"),
+            &signature,
+            &signature,
+            message,
+            &tree,
+            &parents,
+        )?;
+        Ok(oid)
+    }
+
+    #[test]
+    fn test_status_of_repository() -> Result<()> {
+        let dir = tempdir::TempDir::new("mrttest")?;
+        let repo = Repository::init(dir.path())?;
+        repo.set_head("refs/heads/some-branch")?;
+
+        let unborn = get_repo_status(dir.path())?;
+        assert_eq!(unborn.branch, Some(String::from("some-branch")));
+        assert_eq!(unborn.untracked, 0);
+
+        File::create(dir.path().join("file1"))?;
+        commit_all(&repo, "Initial commit")?;
+        File::create(dir.path().join("file2"))?;
+        File::create(dir.path().join("file3"))?;
+        std::fs::write(dir.path().join("file1"), "modified")?;
+
+        let mut index = repo.index()?;
+        index.add_path(Path::new("file2"))?;
+        index.write()?;
+
+        let dirty = get_repo_status(dir.path())?;
+        assert_eq!(dirty.branch, Some(String::from("some-branch")));
+        assert_eq!(dirty.default_branch, Some(String::from(DEFAULT_BRANCH)));
+        assert_eq!(dirty.upstream, None);
+        assert_eq!(dirty.staged, 1);
+        assert_eq!(dirty.unstaged, 1);
+        assert_eq!(dirty.untracked, 1);
+        assert_eq!(dirty.stashes, 0);
+        assert_eq!(dirty.conflicted, 0);
+        assert!(!dirty.detached);
+        assert_eq!(dirty.operation, None);
+        assert_eq!(dirty.error, None);
+
+        // A merge is considered in progress while MERGE_HEAD exists
+        let head = repo
+            .head()?
+            .target()
+            .expect("HEAD should point to a commit");
+        std::fs::write(repo.path().join("MERGE_HEAD"), format!("{}\n", head))?;
+        let merging = get_repo_status(dir.path())?;
+        assert_eq!(merging.operation, Some(String::from("merge")));
+
+        dir.close()?;
+        Ok(())
+    }
+
+    #[test]
+    fn test_ahead_and_behind_upstream() -> Result<()> {
+        let dir = tempdir::TempDir::new("mrttest")?;
+        let repo = Repository::init(dir.path())?;
+        repo.set_head("refs/heads/master")?;
+
+        File::create(dir.path().join("file1"))?;
+        let first = commit_all(&repo, "First")?;
+        File::create(dir.path().join("file2"))?;
+        commit_all(&repo, "Second")?;
+
+        // Fake a remote tracking branch pointing at the first commit
+        repo.remote("origin", "https://example.com/repo.git")?;
+        repo.reference("refs/remotes/origin/master", first, true, "test")?;
+        repo.reference_symbolic(
+            "refs/remotes/origin/HEAD",
+            "refs/remotes/origin/master",
+            true,
+            "test",
+        )?;
+        repo.find_branch("master", BranchType::Local)?
+            .set_upstream(Some("origin/master"))?;
+
+        let status = get_repo_status(dir.path())?;
+        assert_eq!(status.upstream, Some(String::from("origin/master")));
+        assert_eq!(status.ahead, 1);
+        assert_eq!(status.behind, 0);
+        assert_eq!(status.default_branch, Some(String::from("master")));
+
+        dir.close()?;
+        Ok(())
+    }
+
+    #[test]
+    fn test_compare_with_default_branch() -> Result<()> {
+        let dir = tempdir::TempDir::new("mrttest")?;
+        let repo = Repository::init(dir.path())?;
+        repo.set_head("refs/heads/master")?;
+
+        File::create(dir.path().join("file1"))?;
+        let first = commit_all(&repo, "First")?;
+        File::create(dir.path().join("file2"))?;
+        let second = commit_all(&repo, "Second")?;
+
+        repo.remote("origin", "https://example.com/repo.git")?;
+        repo.reference("refs/remotes/origin/master", second, true, "test")?;
+
+        repo.branch("feature", &repo.find_commit(first)?, false)?;
+        repo.set_head("refs/heads/feature")?;
+        repo.checkout_head(Some(git2::build::CheckoutBuilder::new().force()))?;
+
+        let merged = get_repo_status(dir.path())?;
+        assert_eq!(merged.default_ref, Some(String::from("origin/master")));
+        assert_eq!(merged.ahead_of_default, 0);
+        assert_eq!(merged.behind_default, 1);
+        assert_eq!(merged.merged, Some(true));
+
+        File::create(dir.path().join("file3"))?;
+        commit_all(&repo, "Third")?;
+
+        let unmerged = get_repo_status(dir.path())?;
+        assert_eq!(unmerged.ahead_of_default, 1);
+        assert_eq!(unmerged.behind_default, 1);
+        assert_eq!(unmerged.merged, Some(false));
+
+        repo.set_head("refs/heads/master")?;
+        let on_default = get_repo_status(dir.path())?;
+        assert_eq!(on_default.merged, None);
+
+        dir.close()?;
+        Ok(())
+    }
+
+    #[test]
+    fn test_submodules_and_worktrees() -> Result<()> {
+        let dir = tempdir::TempDir::new("mrttest")?;
+        let sub_source_path = dir.path().join("sub-source");
+        let main_path = dir.path().join("main");
+        let worktree_path = dir.path().join("main-worktree");
+
+        let sub_source = Repository::init(&sub_source_path)?;
+        File::create(sub_source_path.join("file1"))?;
+        commit_all(&sub_source, "Sub")?;
+
+        let repo = Repository::init(&main_path)?;
+        File::create(main_path.join("file1"))?;
+        commit_all(&repo, "Main")?;
+
+        let sub_url = sub_source_path.to_string_lossy().to_string();
+        let mut submodule = repo.submodule(&sub_url, Path::new("sub"), true)?;
+        submodule.clone(None)?;
+        submodule.add_finalize()?;
+        commit_all(&repo, "Add submodule")?;
+        repo.worktree("wt", &worktree_path, None)?;
+
+        let in_sync = get_repo_status(&main_path)?;
+        assert!(in_sync.drifted_submodules.is_empty());
+        assert_eq!(in_sync.worktrees, vec![worktree_path]);
+        assert_eq!(get_submodule_paths(&repo), vec![main_path.join("sub")]);
+
+        // Cloning without --recursive leaves the submodule uninitialized
+        let clone_path = dir.path().join("main-clone");
+        let clone = Repository::clone(&main_path.to_string_lossy(), &clone_path)?;
+        assert!(get_repo_status(&clone_path)?.drifted_submodules.is_empty());
+        assert!(get_submodule_paths(&clone).is_empty());
+
+        let sub = Repository::open(main_path.join("sub"))?;
+        File::create(main_path.join("sub").join("file2"))?;
+        commit_all(&sub, "Drift")?;
+
+        let drifted = get_repo_status(&main_path)?;
+        assert_eq!(drifted.drifted_submodules, vec![String::from("sub")]);
+
+        dir.close()?;
+        Ok(())
+    }
+
+    #[test]
+    fn test_local_remote_path() {
+        let workdir = Path::new("/work/repo");
+        assert_eq!(
+            get_local_remote_path("file:///srv/repo.git", workdir),
+            Some(PathBuf::from("/srv/repo.git"))
+        );
+        assert_eq!(
+            get_local_remote_path("../other", workdir),
+            Some(PathBuf::from("/work/repo/../other"))
+        );
+        assert_eq!(
+            get_local_remote_path("/srv/repo.git", workdir),
+            Some(PathBuf::from("/srv/repo.git"))
+        );
+        assert_eq!(
+            get_local_remote_path("https://example.com/repo.git", workdir),
+            None
+        );
+        assert_eq!(
+            get_local_remote_path("git@example.com:user/repo.git", workdir),
+            None
+        );
+    }
+}
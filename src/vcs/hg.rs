@@ -0,0 +1,138 @@
+use super::{wait_with_timeout, FileCounts, RepoStatus, Vcs};
+use anyhow::{anyhow, Result};
+use std::path::Path;
+use std::process::{Command, Stdio};
+use std::time::Duration;
+
+const DEFAULT_BRANCH: &str = "default";
+/// Files mercurial keeps in `.hg` while an operation is in progress
+const OPERATION_FILES: &[(&str, &str)] = &[
+    ("rebasestate", "rebase"),
+    ("histedit-state", "histedit"),
+    ("graftstate", "graft"),
+    ("merge/state", "merge"),
+];
+
+pub struct Mercurial;
+
+impl Vcs for Mercurial {
+    fn name(&self) -> &'static str {
+        "hg"
+    }
+
+    fn detect(&self, path: &Path) -> bool {
+        path.join(".hg").is_dir()
+    }
+
+    fn status(&self, path: &Path) -> Result<RepoStatus> {
+        let counts = parse_status(&run_hg(path, &["status"])?);
+        let conflicted = run_hg(path, &["resolve", "--list"])?
+            .lines()
+            .filter(|line| line.starts_with("U "))
+            .count();
+        let branch = run_hg(path, &["branch"])?.trim().to_string();
+
+        Ok(RepoStatus {
+            path: path.to_path_buf(),
+            branch: Some(branch),
+            default_branch: Some(String::from(DEFAULT_BRANCH)),
+            staged: counts.staged,
+            unstaged: counts.unstaged,
+            untracked: counts.untracked,
+            operation: get_operation(path),
+            conflicted,
+            ..RepoStatus::default()
+        })
+    }
+
+    fn is_modified(&self, path: &Path) -> Result<bool> {
+        Ok(!run_hg(path, &["status"])?.trim().is_empty())
+    }
+
+    fn fetch(&self, path: &Path, timeout: Duration) -> Result<()> {
+        let child = Command::new("hg")
+            .args(["pull", "--quiet"])
+            .current_dir(path)
+            .env("HGPLAIN", "1")
+            .stdin(Stdio::null())
+            .stdout(Stdio::null())
+            .stderr(Stdio::null())
+            .spawn()?;
+
+        wait_with_timeout(child, timeout)
+    }
+}
+
+/// Runs hg with `HGPLAIN` set, so output isn't affected by the users configuration
+fn run_hg(path: &Path, args: &[&str]) -> Result<String> {
+    let output = Command::new("hg")
+        .args(args)
+        .current_dir(path)
+        .env("HGPLAIN", "1")
+        .stdin(Stdio::null())
+        .output()?;
+
+    if !output.status.success() {
+        return Err(anyhow!(
+            "hg {} failed: {}",
+            args.join(" "),
+            String::from_utf8_lossy(&output.stderr).trim()
+        ));
+    }
+    Ok(String::from_utf8_lossy(&output.stdout).to_string())
+}
+
+/// Mercurial has no staging area, so added and removed files are counted as staged
+/// since they are recorded as part of the next commit
+fn parse_status(output: &str) -> FileCounts {
+    let count_matching = |codes: &[char]| {
+        output
+            .lines()
+            .filter(|line| line.chars().next().is_some_and(|c| codes.contains(&c)))
+            .count()
+    };
+
+    FileCounts {
+        staged: count_matching(&['A', 'R']),
+        unstaged: count_matching(&['M', '!']),
+        untracked: count_matching(&['?']),
+        conflicted: 0,
+    }
+}
+
+fn get_operation(path: &Path) -> Option<String> {
+    let hg_dir = path.join(".hg");
+    OPERATION_FILES
+        .iter()
+        .find(|(file, _)| hg_dir.join(file).exists())
+        .map(|(_, operation)| String::from(*operation))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_parse_status() {
+        let output = "M src/main.rs\nA new.rs\nR old.rs\n! gone.rs\n? scratch.txt\n? notes.md\n";
+        let counts = parse_status(output);
+        assert_eq!(counts.staged, 2);
+        assert_eq!(counts.unstaged, 2);
+        assert_eq!(counts.untracked, 2);
+        assert_eq!(parse_status("").untracked, 0);
+    }
+
+    #[test]
+    fn test_operation_in_progress() -> Result<()> {
+        let dir = tempdir::TempDir::new("mrttest")?;
+        std::fs::create_dir_all(dir.path().join(".hg").join("merge"))?;
+        assert!(Mercurial.detect(dir.path()));
+        assert_eq!(get_operation(dir.path()), None);
+
+        std::fs::write(dir.path().join(".hg").join("merge").join("state"), "")?;
+        assert_eq!(get_operation(dir.path()), Some(String::from("merge")));
+
+        dir.close()?;
+        Ok(())
+    }
+}
@@ -0,0 +1,132 @@
+pub mod git;
+pub mod hg;
+pub mod plain;
+
+use anyhow::{anyhow, Result};
+use serde::Serialize;
+use std::path::{Path, PathBuf};
+use std::process::Child;
+use std::thread;
+use std::time::{Duration, Instant};
+
+/// Backends in the order they are detected, the plain directory backend matches every directory
+const BACKENDS: &[&dyn Vcs] = &[&git::Git, &hg::Mercurial, &plain::PlainDir];
+
+/// A version control system that mrt can report status for
+pub trait Vcs: Sync {
+    fn name(&self) -> &'static str;
+
+    /// Whether `path` is the root of a working copy managed by this backend
+    fn detect(&self, path: &Path) -> bool;
+
+    fn status(&self, path: &Path) -> Result<RepoStatus>;
+
+    /// Whether there are any uncommitted changes in the working copy
+    fn is_modified(&self, path: &Path) -> Result<bool>;
+
+    /// Updates remote tracking information, backends without remotes do nothing
+    fn fetch(&self, _path: &Path, _timeout: Duration) -> Result<()> {
+        Ok(())
+    }
+}
+
+#[derive(Serialize, Debug, Default, Eq, PartialEq)]
+pub struct RepoStatus {
+    pub path: PathBuf,
+    /// Name of the backend used, like `git` or `plain`, `None` when the path is missing
+    pub vcs: Option<String>,
+    /// The path doesn't exist, usually because it was moved or deleted
+    pub missing: bool,
+    pub branch: Option<String>,
+    pub default_branch: Option<String>,
+    pub upstream: Option<String>,
+    pub ahead: usize,
+    pub behind: usize,
+    /// Reference the current branch is compared to, like `origin/main`
+    pub default_ref: Option<String>,
+    pub ahead_of_default: usize,
+    pub behind_default: usize,
+    /// Whether the current branch is merged into `default_ref`, `None` when on the default branch
+    pub merged: Option<bool>,
+    pub staged: usize,
+    pub unstaged: usize,
+    pub untracked: usize,
+    pub conflicted: usize,
+    pub stashes: usize,
+    pub detached: bool,
+    /// Operation in progress, like `rebase` or `merge`
+    pub operation: Option<String>,
+    /// Names of submodules where the checked out commit differs from the one recorded in HEAD
+    pub drifted_submodules: Vec<String>,
+    /// Paths of linked worktrees
+    pub worktrees: Vec<PathBuf>,
+    pub error: Option<String>,
+}
+
+impl RepoStatus {
+    pub fn from_error(path: &Path, error: &anyhow::Error) -> Self {
+        Self {
+            path: path.to_path_buf(),
+            error: Some(error.to_string()),
+            ..Self::default()
+        }
+    }
+}
+
+pub struct FileCounts {
+    pub staged: usize,
+    pub unstaged: usize,
+    pub untracked: usize,
+    pub conflicted: usize,
+}
+
+/// Finds the backend managing `path`, falling back to treating it as a plain directory
+pub fn detect(path: &Path) -> &'static dyn Vcs {
+    BACKENDS
+        .iter()
+        .copied()
+        .find(|backend| backend.detect(path))
+        .unwrap_or(&plain::PlainDir)
+}
+
+pub fn get_status(path: &Path) -> RepoStatus {
+    if !path.exists() {
+        return RepoStatus {
+            path: path.to_path_buf(),
+            missing: true,
+            ..RepoStatus::default()
+        };
+    }
+
+    let backend = detect(path);
+    let status = backend
+        .status(path)
+        .unwrap_or_else(|e| RepoStatus::from_error(path, &e));
+
+    RepoStatus {
+        vcs: Some(String::from(backend.name())),
+        ..status
+    }
+}
+
+/// Waits for `child` to exit, killing it if it takes longer than `timeout`
+pub fn wait_with_timeout(mut child: Child, timeout: Duration) -> Result<()> {
+    let started = Instant::now();
+    loop {
+        if let Some(exit_status) = child.try_wait()? {
+            return if exit_status.success() {
+                Ok(())
+            } else {
+                Err(anyhow!("Fetch failed with {}", exit_status))
+            };
+        }
+
+        if started.elapsed() > timeout {
+            child.kill()?;
+            child.wait()?;
+            return Err(anyhow!("Timed out after {} seconds", timeout.as_secs()));
+        }
+
+        thread::sleep(Duration::from_millis(50));
+    }
+}
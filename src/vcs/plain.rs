@@ -0,0 +1,33 @@
+use super::{RepoStatus, Vcs};
+use anyhow::{anyhow, Result};
+use std::path::Path;
+
+pub const NAME: &str = "plain";
+
+/// Directories without version control, only existence is reported
+pub struct PlainDir;
+
+impl Vcs for PlainDir {
+    fn name(&self) -> &'static str {
+        NAME
+    }
+
+    fn detect(&self, path: &Path) -> bool {
+        path.is_dir()
+    }
+
+    fn status(&self, path: &Path) -> Result<RepoStatus> {
+        if !path.is_dir() {
+            return Err(anyhow!("{} is not a directory", path.display()));
+        }
+
+        Ok(RepoStatus {
+            path: path.to_path_buf(),
+            ..RepoStatus::default()
+        })
+    }
+
+    fn is_modified(&self, _path: &Path) -> Result<bool> {
+        Ok(false)
+    }
+}